@@ -1,26 +1,143 @@
-use std::collections::{HashMap, HashSet};
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+#[derive(Debug)]
+enum CardError {
+    MissingHeader,
+    BadId(String),
+    MissingSeparator,
+    BadNumber(String),
+    DuplicateWinner(usize),
+    DuplicateNumber(usize),
+}
+impl Display for CardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CardError::MissingHeader => write!(f, "missing \"Card N:\" header"),
+            CardError::BadId(id) => write!(f, "bad card id {id:?}"),
+            CardError::MissingSeparator => write!(f, "missing '|' separator"),
+            CardError::BadNumber(num) => write!(f, "bad number {num:?}"),
+            CardError::DuplicateWinner(num) => write!(f, "winning number {num} listed twice"),
+            CardError::DuplicateNumber(num) => write!(f, "number {num} listed twice"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Scratchcard {
+    id: usize,
+    winners: Vec<usize>,
+    numbers: Vec<usize>,
+}
+impl Scratchcard {
+    fn num_matches(&self) -> usize {
+        self.numbers
+            .iter()
+            .filter(|num| self.winners.contains(num))
+            .count()
+    }
+}
+impl FromStr for Scratchcard {
+    type Err = CardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (header, card) = s.split_once(':').ok_or(CardError::MissingHeader)?;
+        let id = header
+            .strip_prefix("Card")
+            .ok_or(CardError::MissingHeader)?
+            .trim();
+        let id = id.parse().map_err(|_| CardError::BadId(id.to_string()))?;
+
+        let (winners, numbers) = card.split_once('|').ok_or(CardError::MissingSeparator)?;
+        let winners = parse_numbers(winners, CardError::DuplicateWinner)?;
+        let numbers = parse_numbers(numbers, CardError::DuplicateNumber)?;
+
+        Ok(Self {
+            id,
+            winners,
+            numbers,
+        })
+    }
+}
+
+fn parse_numbers(s: &str, duplicate: impl Fn(usize) -> CardError) -> Result<Vec<usize>, CardError> {
+    let mut result = Vec::new();
+    for x in s.split_ascii_whitespace() {
+        let num = x.parse().map_err(|_| CardError::BadNumber(x.to_string()))?;
+        if result.contains(&num) {
+            return Err(duplicate(num));
+        }
+        result.push(num);
+    }
+    Ok(result)
+}
+
+#[derive(Debug, Clone)]
+enum Scoring {
+    Doubling,
+    Linear,
+    Fibonacci,
+    // points for 1, 2, 3... matches; matches past the end of the table score the last entry
+    Table(Vec<usize>),
+}
+impl Scoring {
+    fn score(&self, num_matches: usize) -> usize {
+        if num_matches == 0 {
+            return 0;
+        }
+
+        match self {
+            Scoring::Doubling => 2usize.pow((num_matches - 1) as u32),
+            Scoring::Linear => num_matches,
+            Scoring::Fibonacci => {
+                let (mut a, mut b) = (1, 2);
+                for _ in 1..num_matches {
+                    (a, b) = (b, a + b);
+                }
+                a
+            }
+            Scoring::Table(points) => points
+                .get(num_matches - 1)
+                .or(points.last())
+                .cloned()
+                .unwrap_or(0),
+        }
+    }
+}
+impl FromStr for Scoring {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "doubling" => Ok(Scoring::Doubling),
+            "linear" => Ok(Scoring::Linear),
+            "fibonacci" => Ok(Scoring::Fibonacci),
+            _ => {
+                let table = s
+                    .strip_prefix("table:")
+                    .ok_or_else(|| format!("unknown scoring {s}"))?;
+                table
+                    .split(',')
+                    .map(|x| x.trim().parse().map_err(|_| format!("bad points {x}")))
+                    .collect::<Result<_, _>>()
+                    .map(Scoring::Table)
+            }
+        }
+    }
+}
 
 fn main() {
-    let cards: Vec<usize> = std::fs::read_to_string("input")
+    let scoring: Scoring = std::env::args()
+        .nth(1)
+        .map(|arg| arg.parse().unwrap())
+        .unwrap_or(Scoring::Doubling);
+
+    let cards: Vec<Scratchcard> = std::fs::read_to_string("input")
         .unwrap()
         .lines()
-        .map(|card| {
-            let (_, card) = card.split_at(card.find(":").unwrap());
-            let card = &card[1..];
-            let (winners, numbers) = card.split_at(card.find("|").unwrap());
-            let winners = winners
-                .trim()
-                .split_ascii_whitespace()
-                .map(|x| x.parse().unwrap())
-                .collect::<HashSet<usize>>();
-
-            let numbers = &numbers[1..]
-                .trim()
-                .split_ascii_whitespace()
-                .map(|x| x.parse().unwrap())
-                .collect::<HashSet<usize>>();
-
-            winners.intersection(&numbers).count()
+        .enumerate()
+        .map(|(line, card)| {
+            card.parse()
+                .unwrap_or_else(|e| panic!("line {}: {e}", line + 1))
         })
         .collect();
 
@@ -28,25 +145,19 @@ fn main() {
         "{}",
         cards
             .iter()
-            .map(|num_matches| if *num_matches == 0 {
-                0
-            } else {
-                2usize.pow((num_matches - 1) as u32)
-            })
+            .map(|card| scoring.score(card.num_matches()))
             .sum::<usize>()
     );
 
     let mut multipliers = HashMap::new();
-    multipliers.insert(1, 1u32);
 
     let mut result2 = 0u32;
-    for (idx, num_matches) in cards.into_iter().enumerate() {
-        let idx = idx + 1;
-        let current_multiplier = *multipliers.entry(idx).or_insert(1);
+    for card in &cards {
+        let current_multiplier = *multipliers.entry(card.id).or_insert(1);
 
         result2 += current_multiplier;
-        for i in 1..num_matches + 1 {
-            *multipliers.entry(idx + i).or_insert(1) += current_multiplier;
+        for i in 1..card.num_matches() + 1 {
+            *multipliers.entry(card.id + i).or_insert(1) += current_multiplier;
         }
     }
 
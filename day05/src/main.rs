@@ -1,22 +1,10 @@
-use std::{marker::PhantomData, ops::Range};
+use std::{collections::HashMap, fmt::Display, marker::PhantomData, ops::Range, str::FromStr};
 
 use synonym::Synonym;
 
 #[derive(Synonym)]
 struct Seed(i64);
 #[derive(Synonym)]
-struct Soil(i64);
-#[derive(Synonym)]
-struct Fertilizer(i64);
-#[derive(Synonym)]
-struct Water(i64);
-#[derive(Synonym)]
-struct Light(i64);
-#[derive(Synonym)]
-struct Temperature(i64);
-#[derive(Synonym)]
-struct Humidity(i64);
-#[derive(Synonym)]
 struct Location(i64);
 
 #[derive(Debug, Clone)]
@@ -25,13 +13,293 @@ struct MappingRange {
     offset: i64,
     len: i64,
 }
+impl MappingRange {
+    fn src_range(&self) -> Range<i64> {
+        self.src_start..(self.src_start + self.len)
+    }
+
+    fn dest_range(&self) -> Range<i64> {
+        (self.src_start + self.offset)..(self.src_start + self.offset + self.len)
+    }
+}
+
+// A set of values stored as sorted, disjoint, non-adjacent ranges
+#[derive(Debug, Clone)]
+struct RangeSet<T> {
+    ranges: Vec<Range<i64>>,
+    _phantom: PhantomData<T>,
+}
+impl<T> RangeSet<T> {
+    fn from_ranges(ranges: impl IntoIterator<Item = Range<i64>>) -> Self {
+        let mut ranges: Vec<_> = ranges.into_iter().filter(|r| r.start < r.end).collect();
+        ranges.sort_by_key(|r| r.start);
+
+        // coalesce overlapping and adjacent ranges
+        let mut merged: Vec<Range<i64>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+
+        Self {
+            ranges: merged,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        Self::from_ranges(self.ranges.iter().chain(&other.ranges).cloned())
+    }
+
+    fn intersection(&self, other: &Self) -> Self {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (&self.ranges[i], &other.ranges[j]);
+            let start = a.start.max(b.start);
+            let end = a.end.min(b.end);
+            if start < end {
+                ranges.push(start..end);
+            }
+
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        // pieces of two canonical sets can't touch, so no need to merge again
+        Self {
+            ranges,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn difference(&self, other: &Self) -> Self {
+        let mut ranges = Vec::new();
+        let mut j = 0;
+
+        for range in &self.ranges {
+            while j < other.ranges.len() && other.ranges[j].end <= range.start {
+                j += 1;
+            }
+
+            let mut start = range.start;
+            for cut in other.ranges[j..]
+                .iter()
+                .take_while(|cut| cut.start < range.end)
+            {
+                if cut.start > start {
+                    ranges.push(start..cut.start);
+                }
+                start = start.max(cut.end);
+            }
+            if start < range.end {
+                ranges.push(start..range.end);
+            }
+        }
+
+        Self {
+            ranges,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn shifted<U>(&self, offset: i64) -> RangeSet<U> {
+        RangeSet {
+            ranges: self
+                .ranges
+                .iter()
+                .map(|r| (r.start + offset)..(r.end + offset))
+                .collect(),
+            _phantom: PhantomData,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    // number of values in the set
+    fn size(&self) -> i64 {
+        self.ranges.iter().map(|r| r.end - r.start).sum()
+    }
+
+    fn min(&self) -> Option<T>
+    where
+        T: From<i64>,
+    {
+        self.ranges.first().map(|r| r.start.into())
+    }
+}
+
+// A run of inputs that all share the same offset, lasting until the next segment starts
+#[derive(Debug, Clone)]
+struct Segment {
+    start: i64,
+    offset: i64,
+}
 
 #[derive(Debug)]
 struct Mapping<K, V> {
     // (source start, offset, length)
     ranges: Vec<MappingRange>,
+    // sorted by start, covering the ranges and the gaps between and after them;
+    // anything before the first segment isn't moved
+    index: Vec<Segment>,
     _phantom: PhantomData<(K, V)>,
 }
+impl<K, V> Mapping<K, V> {
+    // sorts the ranges, drops ones that don't move anything, and joins neighbours with equal offsets
+    fn from_ranges(mut ranges: Vec<MappingRange>) -> Self {
+        ranges.retain(|range| range.offset != 0 && range.len > 0);
+        ranges.sort_by_key(|range| range.src_start);
+
+        let mut merged: Vec<MappingRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last)
+                    if last.offset == range.offset
+                        && last.src_start + last.len == range.src_start =>
+                {
+                    last.len += range.len
+                }
+                _ => merged.push(range),
+            }
+        }
+
+        let mut index: Vec<Segment> = Vec::with_capacity(2 * merged.len());
+        for range in &merged {
+            if index.last().is_some_and(|gap| gap.start == range.src_start) {
+                index.pop();
+            }
+            index.push(Segment {
+                start: range.src_start,
+                offset: range.offset,
+            });
+            index.push(Segment {
+                start: range.src_start + range.len,
+                offset: 0,
+            });
+        }
+
+        Mapping {
+            ranges: merged,
+            index,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn retyped<K2, V2>(self) -> Mapping<K2, V2> {
+        Mapping {
+            ranges: self.ranges,
+            index: self.index,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn offset_at(&self, input: i64) -> i64 {
+        match self.index.partition_point(|segment| segment.start <= input) {
+            0 => 0,
+            idx => self.index[idx - 1].offset,
+        }
+    }
+
+    fn domain(&self) -> RangeSet<K> {
+        RangeSet::from_ranges(self.ranges.iter().map(MappingRange::src_range))
+    }
+
+    // splits the input into pieces that are each moved by a single offset (0 for gaps)
+    fn segments(&self, input: Range<i64>) -> Vec<(Range<i64>, i64)> {
+        let mut result = Vec::new();
+        let mut start = input.start;
+        let mut offset = self.offset_at(start);
+
+        let next = self.index.partition_point(|segment| segment.start <= start);
+        for segment in &self.index[next..] {
+            if segment.start >= input.end {
+                break;
+            }
+            result.push((start..segment.start, offset));
+            start = segment.start;
+            offset = segment.offset;
+        }
+        if start < input.end {
+            result.push((start..input.end, offset));
+        }
+
+        result
+    }
+
+    // precomputes the mapping equivalent to applying self, then next, or None if a combined
+    // offset doesn't fit in an i64
+    fn then<W>(&self, next: &Mapping<V, W>) -> Option<Mapping<K, W>> {
+        let mut ranges = Vec::new();
+
+        for mapping in &self.ranges {
+            for (piece, offset) in next.segments(mapping.dest_range()) {
+                ranges.push(MappingRange {
+                    src_start: piece.start - mapping.offset,
+                    offset: mapping.offset.checked_add(offset)?,
+                    len: piece.end - piece.start,
+                });
+            }
+        }
+
+        // values we don't move go straight through to next
+        let passthrough = next.domain().difference(&self.domain().shifted(0));
+        for range in passthrough.ranges {
+            for (piece, offset) in next.segments(range) {
+                ranges.push(MappingRange {
+                    src_start: piece.start,
+                    offset,
+                    len: piece.end - piece.start,
+                });
+            }
+        }
+
+        Some(Mapping::from_ranges(ranges))
+    }
+
+    // all inputs that map into target
+    fn preimage(&self, target: &RangeSet<V>) -> RangeSet<K> {
+        let moved = RangeSet::from_ranges(self.ranges.iter().flat_map(|mapping| {
+            target
+                .intersection(&RangeSet::from_ranges([mapping.dest_range()]))
+                .shifted::<K>(-mapping.offset)
+                .ranges
+        }));
+        let unmoved = target.shifted(0).difference(&self.domain());
+
+        moved.union(&unmoved)
+    }
+
+    // None if the mapping isn't one-to-one, i.e. the moved ranges don't land exactly
+    // on the values they vacate
+    fn invert(&self) -> Option<Mapping<V, K>> {
+        let images = RangeSet::<V>::from_ranges(self.ranges.iter().map(MappingRange::dest_range));
+        let moved: i64 = self.ranges.iter().map(|range| range.len).sum();
+        if images.size() != moved || images.ranges != self.domain().ranges {
+            return None;
+        }
+
+        Some(Mapping::from_ranges(
+            self.ranges
+                .iter()
+                .map(|range| MappingRange {
+                    src_start: range.src_start + range.offset,
+                    offset: -range.offset,
+                    len: range.len,
+                })
+                .collect(),
+        ))
+    }
+}
+
 impl<K, V> Mapping<K, V>
 where
     K: AsRef<i64> + Clone + PartialEq,
@@ -39,151 +307,242 @@ where
 {
     fn apply(&self, input: &K) -> V {
         let input = *input.as_ref();
-
-        if let Some(range) = self.find_range(input) {
-            (input + range.offset).into()
-        } else {
-            input.into()
-        }
+        (input + self.offset_at(input)).into()
     }
 
-    fn apply_ranges(&self, input: &[Range<K>]) -> Vec<Range<V>> {
-        let mut result = Vec::new();
+    fn apply_ranges(&self, input: &RangeSet<K>) -> RangeSet<V> {
+        RangeSet::from_ranges(input.ranges.iter().flat_map(|range| {
+            self.segments(range.clone())
+                .into_iter()
+                .map(|(piece, offset)| (piece.start + offset)..(piece.end + offset))
+        }))
+    }
+}
 
-        for range in input {
-            let mut start = *range.start.as_ref();
-            let end = *range.end.as_ref();
-
-            while start != end {
-                if let Some(mapping) = self.find_range(start) {
-                    // we're covered up to the end of this mapping
-                    let end_of_cover = end.min(mapping.src_start + mapping.len);
-                    result.push((start + mapping.offset)..(end_of_cover + mapping.offset));
-                    start = end_of_cover;
-                } else if let Some(next_mapping) = self.find_next_range(start) {
-                    // we're not covered, up to the start of the next mapping
-                    let end_of_uncover = end.min(next_mapping.src_start);
-                    result.push(start..end_of_uncover);
-                    start = end_of_uncover;
-                } else {
-                    // we're not covered and there are no mappings after the input
-                    result.push(start..end);
-                    start = end;
-                }
+#[derive(Debug)]
+enum AlmanacError {
+    MissingSeeds,
+    BadSeed(String),
+    BadHeader(String),
+    BadRange {
+        map: String,
+        line: String,
+    },
+    Overlap {
+        map: String,
+        side: &'static str,
+        first: Range<i64>,
+        second: Range<i64>,
+    },
+    DuplicateSource(String),
+    NoPath {
+        from: String,
+        to: String,
+    },
+    OffsetOverflow {
+        from: String,
+        to: String,
+    },
+}
+impl Display for AlmanacError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlmanacError::MissingSeeds => write!(f, "missing \"seeds:\" line"),
+            AlmanacError::BadSeed(seed) => write!(f, "bad seed {seed:?}"),
+            AlmanacError::BadHeader(header) => write!(f, "bad map header {header:?}"),
+            AlmanacError::BadRange { map, line } => write!(f, "{map}: bad range {line:?}"),
+            AlmanacError::Overlap {
+                map,
+                side,
+                first,
+                second,
+            } => write!(f, "{map}: {side} ranges {first:?} and {second:?} overlap"),
+            AlmanacError::DuplicateSource(category) => {
+                write!(f, "more than one map from {category}")
+            }
+            AlmanacError::NoPath { from, to } => write!(f, "no chain of maps from {from} to {to}"),
+            AlmanacError::OffsetOverflow { from, to } => {
+                write!(
+                    f,
+                    "chaining the maps from {from} to {to} overflows an offset"
+                )
             }
         }
-
-        result.sort_by_key(|r| r.start);
-        // could merge/simplify ranges here
-        result.into_iter()
-            .map(|range| range.start.into()..range.end.into())
-            .collect()
     }
+}
 
-    fn find_range(&self, input: i64) -> Option<MappingRange> {
-        for range in &self.ranges {
-            if range.src_start <= input && range.src_start + range.len > input {
-                return Some(range.clone());
+#[derive(Debug)]
+struct Almanac {
+    seeds: Vec<i64>,
+    // source category -> (destination category, mapping)
+    maps: HashMap<String, (String, Mapping<(), ()>)>,
+}
+impl Almanac {
+    fn chain<K, V>(&self, from: &str, to: &str) -> Result<Mapping<K, V>, AlmanacError> {
+        let no_path = || AlmanacError::NoPath {
+            from: from.to_string(),
+            to: to.to_string(),
+        };
+
+        let mut result = Mapping::<(), ()>::from_ranges(Vec::new());
+        let mut category = from;
+        let mut steps = 0;
+        while category != to {
+            let (next, mapping) = self.maps.get(category).ok_or_else(no_path)?;
+            result = result
+                .then(mapping)
+                .ok_or_else(|| AlmanacError::OffsetOverflow {
+                    from: from.to_string(),
+                    to: next.clone(),
+                })?;
+            category = next;
+
+            // every map can only be used once on the way, otherwise we're going in circles
+            steps += 1;
+            if steps > self.maps.len() {
+                return Err(no_path());
             }
         }
 
-        None
+        Ok(result.retyped())
     }
+}
+impl FromStr for Almanac {
+    type Err = AlmanacError;
 
-    // finds the range, or the next range after the input
-    fn find_next_range(&self, input: i64) -> Option<MappingRange> {
-        for range in &self.ranges {
-            if range.src_start + range.len > input {
-                return Some(range.clone());
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut data = s.split("\n\n");
+
+        let seeds = data
+            .next()
+            .and_then(|seeds| seeds.trim().strip_prefix("seeds:"))
+            .ok_or(AlmanacError::MissingSeeds)?
+            .split_ascii_whitespace()
+            .map(|x| x.parse().map_err(|_| AlmanacError::BadSeed(x.to_string())))
+            .collect::<Result<_, _>>()?;
+
+        let mut maps = HashMap::new();
+        for section in data.filter(|section| !section.trim().is_empty()) {
+            let (from, to, mapping) = parse_map(section.trim())?;
+            if maps.contains_key(&from) {
+                return Err(AlmanacError::DuplicateSource(from));
             }
+            maps.insert(from, (to, mapping));
         }
 
-        None
+        Ok(Self { seeds, maps })
     }
 }
 
-fn parse_map<K, V>(data: &str) -> Mapping<K, V> {
-    let mut ranges: Vec<_> = data
-        .lines()
-        .skip(1)
+fn parse_map(data: &str) -> Result<(String, String, Mapping<(), ()>), AlmanacError> {
+    let mut lines = data.lines();
+    let header = lines.next().unwrap_or_default();
+    let (from, to) = header
+        .strip_suffix(" map:")
+        .and_then(|name| name.split_once("-to-"))
+        .ok_or_else(|| AlmanacError::BadHeader(header.to_string()))?;
+    let name = format!("{from}-to-{to}");
+
+    let mut ranges = lines
         .map(|line| {
-            let mut parts = line.split_ascii_whitespace();
-            let dest_start: i64 = parts.next().unwrap().parse().unwrap();
-            let src_start: i64 = parts.next().unwrap().parse().unwrap();
-            let len = parts.next().unwrap().parse().unwrap();
-            let offset = dest_start - src_start;
-            MappingRange {
+            let bad_range = || AlmanacError::BadRange {
+                map: name.clone(),
+                line: line.to_string(),
+            };
+
+            let parts = line
+                .split_ascii_whitespace()
+                .map(|x| x.parse::<i64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| bad_range())?;
+            let [dest_start, src_start, len] = parts[..] else {
+                return Err(bad_range());
+            };
+            if len <= 0
+                || src_start.checked_add(len).is_none()
+                || dest_start.checked_add(len).is_none()
+            {
+                return Err(bad_range());
+            }
+
+            Ok(MappingRange {
                 src_start,
-                offset,
+                offset: dest_start.checked_sub(src_start).ok_or_else(bad_range)?,
                 len,
-            }
+            })
         })
-        .collect();
-
-    ranges.sort_by_key(|m| m.src_start);
+        .collect::<Result<Vec<_>, _>>()?;
 
-    Mapping {
-        ranges,
-        _phantom: PhantomData,
+    for (side, range_of) in [
+        (
+            "source",
+            MappingRange::src_range as fn(&MappingRange) -> Range<i64>,
+        ),
+        ("destination", MappingRange::dest_range),
+    ] {
+        ranges.sort_by_key(|range| range_of(range).start);
+        for pair in ranges.windows(2) {
+            let (first, second) = (range_of(&pair[0]), range_of(&pair[1]));
+            if first.end > second.start {
+                return Err(AlmanacError::Overlap {
+                    map: name,
+                    side,
+                    first,
+                    second,
+                });
+            }
+        }
     }
+
+    Ok((
+        from.to_string(),
+        to.to_string(),
+        Mapping::from_ranges(ranges),
+    ))
 }
 
 fn main() {
-    let input = std::fs::read_to_string("input").unwrap();
-    let mut data = input.split("\n\n");
-
-    let seeds = data.next().unwrap();
-    let (_, seeds) = seeds.split_at(seeds.find(":").unwrap());
-    let seeds_p1 = seeds[1..]
-        .trim()
-        .split_ascii_whitespace()
-        .map(|x| Seed(x.parse().unwrap()));
-
-    let seed_ranges = seeds[1..]
-        .trim()
-        .split_ascii_whitespace()
-        .collect::<Vec<_>>()
-        .chunks_exact(2)
-        .map(|chunk| {
-            let start: i64 = chunk[0].parse().unwrap();
-            let len: i64 = chunk[1].parse().unwrap();
-            Seed(start)..Seed(start + len)
-        })
-        .collect::<Vec<_>>();
+    let almanac: Almanac = std::fs::read_to_string("input")
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|e| panic!("{e}"));
 
-    let seed_to_soil = parse_map::<Seed, Soil>(data.next().unwrap());
-    let soil_to_fertilizer = parse_map::<Soil, Fertilizer>(data.next().unwrap());
-    let fertilizer_to_water = parse_map::<Fertilizer, Water>(data.next().unwrap());
-    let water_to_light = parse_map::<Water, Light>(data.next().unwrap());
-    let light_to_temp = parse_map::<Light, Temperature>(data.next().unwrap());
-    let temp_to_humidity = parse_map::<Temperature, Humidity>(data.next().unwrap());
-    let humidity_to_location = parse_map::<Humidity, Location>(data.next().unwrap());
+    let seeds_p1 = almanac.seeds.iter().map(|x| Seed(*x));
+    let seed_ranges = RangeSet::<Seed>::from_ranges(
+        almanac
+            .seeds
+            .chunks_exact(2)
+            .map(|chunk| chunk[0]..(chunk[0] + chunk[1])),
+    );
+
+    let seed_to_location: Mapping<Seed, Location> = almanac
+        .chain("seed", "location")
+        .unwrap_or_else(|e| panic!("{e}"));
 
     let result1 = seeds_p1
-        .map(|seed| {
-            humidity_to_location.apply(
-                &temp_to_humidity.apply(
-                    &light_to_temp.apply(
-                        &water_to_light.apply(
-                            &fertilizer_to_water
-                                .apply(&soil_to_fertilizer.apply(&seed_to_soil.apply(&seed))),
-                        ),
-                    ),
-                ),
-            )
-        })
+        .map(|seed| seed_to_location.apply(&seed))
         .min()
         .unwrap();
 
     println!("{result1:?}");
 
-    let location_ranges = humidity_to_location.apply_ranges(&temp_to_humidity.apply_ranges(
-        &light_to_temp.apply_ranges(&water_to_light.apply_ranges(
-            &fertilizer_to_water.apply_ranges(
-                &soil_to_fertilizer.apply_ranges(&seed_to_soil.apply_ranges(&seed_ranges)),
-            ),
-        )),
-    ));
+    let location_ranges = seed_to_location.apply_ranges(&seed_ranges);
     dbg!(location_ranges.len());
-    println!("{:?}", location_ranges[0].start);
+    let lowest_location = location_ranges.min().unwrap();
+    println!("{lowest_location:?}");
+
+    let lowest_seed = match seed_to_location.invert() {
+        Some(location_to_seed) => location_to_seed.apply(&lowest_location),
+        None => {
+            let lowest = *lowest_location.as_ref();
+            seed_to_location
+                .preimage(&RangeSet::from_ranges(std::iter::once(
+                    lowest..(lowest + 1),
+                )))
+                .intersection(&seed_ranges)
+                .min()
+                .unwrap()
+        }
+    };
+    println!("{lowest_seed:?}");
 }
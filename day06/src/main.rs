@@ -1,94 +1,407 @@
-fn p2() {
-    let input = std::fs::read_to_string("input").unwrap();
-    let mut data = input.lines();
-    let time = data
-        .next()
-        .unwrap()
-        .split(":")
-        .nth(1)
-        .unwrap()
-        .trim()
-        .split_ascii_whitespace()
-        .collect::<String>()
-        .parse::<i64>()
-        .unwrap();
-
-    let distance = data
-        .next()
-        .unwrap()
-        .split(":")
-        .nth(1)
-        .unwrap()
-        .trim()
-        .split_ascii_whitespace()
-        .collect::<String>()
-        .parse::<i64>()
-        .unwrap();
-
-    let result: i64 = std::iter::once(time)
-        .zip(std::iter::once(distance))
-        .map(|(max_time, max_distance)| {
-            let mut num_ways = 0;
-            for time_charged in 1..max_time {
-                let time_remaining = max_time - time_charged;
-                let speed = time_charged;
-                let total_distance = speed * time_remaining;
-                if total_distance > max_distance {
-                    num_ways += 1;
+use std::{cmp::Ordering, fmt::Display, ops::RangeInclusive, str::FromStr};
+
+#[derive(Debug, Clone, Copy)]
+struct Race {
+    time: u128,
+    record: u128,
+}
+impl Race {
+    fn ways_to_win(&self, model: &dyn BoatModel) -> u128 {
+        model
+            .winning_charge_times(self)
+            .map(|times| times.end() - times.start() + 1)
+            .unwrap_or(0)
+    }
+}
+
+// How far a boat gets in a race after charging for some time. The default analysis assumes
+// the distance rises to a single peak (possibly flat) and then falls.
+trait BoatModel {
+    // None when the distance doesn't fit in a u128, which beats any record
+    fn distance(&self, race_time: u128, time_charged: u128) -> Option<u128>;
+
+    fn beats_record(&self, race: &Race, time_charged: u128) -> bool {
+        self.distance(race.time, time_charged)
+            .is_none_or(|distance| distance > race.record)
+    }
+
+    fn optimal_charge_time(&self, race: &Race) -> u128 {
+        let distance = |t| self.distance(race.time, t).unwrap_or(u128::MAX);
+
+        // ternary search for the peak
+        let (mut lo, mut hi) = (0, race.time);
+        while hi - lo > 2 {
+            let m1 = lo + (hi - lo) / 3;
+            let m2 = hi - (hi - lo) / 3;
+            match distance(m1).cmp(&distance(m2)) {
+                Ordering::Less => lo = m1 + 1,
+                Ordering::Greater => hi = m2 - 1,
+                Ordering::Equal => (lo, hi) = (m1, m2),
+            }
+        }
+
+        (lo..=hi).max_by_key(|t| distance(*t)).unwrap()
+    }
+
+    fn winning_charge_times(&self, race: &Race) -> Option<RangeInclusive<u128>> {
+        let wins = |t| self.beats_record(race, t);
+
+        let peak = self.optimal_charge_time(race);
+        if !wins(peak) {
+            return None;
+        }
+
+        // the distance only rises before the peak and only falls after it
+        let (mut lo, mut hi) = (0, peak);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if wins(mid) {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        let first = lo;
+
+        let (mut lo, mut hi) = (peak, race.time);
+        while lo < hi {
+            let mid = hi - (hi - lo) / 2;
+            if wins(mid) {
+                lo = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+        let last = lo;
+
+        Some(first..=last)
+    }
+}
+
+// speed is the charge time
+struct Linear;
+impl BoatModel for Linear {
+    fn distance(&self, race_time: u128, time_charged: u128) -> Option<u128> {
+        let time_remaining = race_time - time_charged;
+        let speed = time_charged;
+        speed.checked_mul(time_remaining)
+    }
+
+    fn optimal_charge_time(&self, race: &Race) -> u128 {
+        race.time / 2
+    }
+
+    // the charge times that beat the record are the integers strictly between the roots of
+    // t * (time - t) = record
+    fn winning_charge_times(&self, race: &Race) -> Option<RangeInclusive<u128>> {
+        let time = race.time;
+        let wins = |t| self.beats_record(race, t);
+
+        let lowest = match time.checked_mul(time) {
+            Some(time_squared) => {
+                // no real roots means the peak can't beat the record
+                let discriminant = time_squared.checked_sub(race.record.checked_mul(4)?)?;
+                let mut lowest = (time - discriminant.isqrt()) / 2;
+
+                // the integer square root can be off by one either way, so nudge onto the
+                // boundary
+                while lowest <= time / 2 && !wins(lowest) {
+                    lowest += 1;
+                }
+                while lowest > 0 && wins(lowest - 1) {
+                    lowest -= 1;
+                }
+                lowest
+            }
+            // too big to square, but the distance only rises up to time / 2
+            None => {
+                let (mut lo, mut hi) = (0, time / 2 + 1);
+                while lo < hi {
+                    let mid = lo + (hi - lo) / 2;
+                    if wins(mid) {
+                        hi = mid;
+                    } else {
+                        lo = mid + 1;
+                    }
+                }
+                lo
+            }
+        };
+
+        if lowest > time / 2 {
+            return None;
+        }
+
+        // the distance curve is symmetric around time / 2
+        Some(lowest..=time - lowest)
+    }
+}
+
+// speed is the square of the charge time
+struct QuadraticAcceleration;
+impl BoatModel for QuadraticAcceleration {
+    fn distance(&self, race_time: u128, time_charged: u128) -> Option<u128> {
+        let time_remaining = race_time - time_charged;
+        if time_remaining == 0 {
+            return Some(0);
+        }
+        let speed = time_charged.checked_mul(time_charged)?;
+        speed.checked_mul(time_remaining)
+    }
+}
+
+// speed is the charge time, up to a limit
+struct CappedSpeed {
+    max_speed: u128,
+}
+impl BoatModel for CappedSpeed {
+    fn distance(&self, race_time: u128, time_charged: u128) -> Option<u128> {
+        let time_remaining = race_time - time_charged;
+        let speed = time_charged.min(self.max_speed);
+        speed.checked_mul(time_remaining)
+    }
+}
+
+// speed starts at the charge time and drops by a fixed amount every millisecond
+struct Drag {
+    deceleration: u128,
+}
+impl BoatModel for Drag {
+    fn distance(&self, race_time: u128, time_charged: u128) -> Option<u128> {
+        let time_remaining = race_time - time_charged;
+        let speed = time_charged;
+        let deceleration = self.deceleration;
+
+        // milliseconds spent moving before we either stop or run out of time
+        let moving = time_remaining.min(speed.div_ceil(deceleration));
+        match moving {
+            0 => Some(0),
+            1 => Some(speed),
+            _ => {
+                // arithmetic series from speed down to the speed in the last moving
+                // millisecond, which is still positive; the sum is at least first + last,
+                // so if that overflows, so does the distance
+                let last = speed - (moving - 1) * deceleration;
+                let first_plus_last = speed.checked_add(last)?;
+                // one of the two factors is even
+                if moving.is_multiple_of(2) {
+                    (moving / 2).checked_mul(first_plus_last)
+                } else {
+                    moving.checked_mul(first_plus_last / 2)
                 }
             }
+        }
+    }
+}
+
+fn parse_model(name: &str) -> Box<dyn BoatModel> {
+    match name.split_once(':') {
+        None if name == "linear" => Box::new(Linear),
+        None if name == "quadratic" => Box::new(QuadraticAcceleration),
+        Some(("capped", max_speed)) => Box::new(CappedSpeed {
+            max_speed: max_speed.parse().unwrap(),
+        }),
+        Some(("drag", deceleration)) => {
+            let deceleration = deceleration.parse().unwrap();
+            assert!(deceleration > 0, "drag needs a positive deceleration");
+            Box::new(Drag { deceleration })
+        }
+        _ => panic!("unknown boat model {name}"),
+    }
+}
+
+#[derive(Debug)]
+enum SheetError {
+    MissingLine(&'static str),
+    BadNumber { line: &'static str, value: String },
+    ColumnMismatch { times: usize, distances: usize },
+    Empty,
+    KernedTooLarge(&'static str),
+}
+impl Display for SheetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SheetError::MissingLine(line) => write!(f, "missing \"{line}:\" line"),
+            SheetError::BadNumber { line, value } => {
+                write!(f, "{line}: {value:?} is not a number")
+            }
+            SheetError::ColumnMismatch { times, distances } => {
+                write!(f, "{times} times but {distances} distances")
+            }
+            SheetError::Empty => write!(f, "no races on the sheet"),
+            SheetError::KernedTooLarge(line) => write!(f, "{line}: kerned number is too large"),
+        }
+    }
+}
+
+// The races one per column, plus each line's digits run together for reading it as a single
+// race with the spaces kerned away
+#[derive(Debug)]
+struct RaceSheet {
+    races: Vec<Race>,
+    time_digits: String,
+    distance_digits: String,
+}
+impl RaceSheet {
+    fn races(&self) -> &[Race] {
+        &self.races
+    }
+
+    fn kerned_race(&self) -> Result<Race, SheetError> {
+        let kern =
+            |digits: &str, line| digits.parse().map_err(|_| SheetError::KernedTooLarge(line));
 
-            num_ways
+        Ok(Race {
+            time: kern(&self.time_digits, "Time")?,
+            record: kern(&self.distance_digits, "Distance")?,
         })
-        .product();
+    }
+}
+impl FromStr for RaceSheet {
+    type Err = SheetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        let mut parse_line = |name| {
+            let fields = lines
+                .next()
+                .and_then(|line| line.strip_prefix(name))
+                .and_then(|line| line.strip_prefix(':'))
+                .ok_or(SheetError::MissingLine(name))?
+                .split_ascii_whitespace();
+
+            let mut numbers = Vec::new();
+            let mut digits = String::new();
+            for value in fields {
+                numbers.push(value.parse::<u128>().map_err(|_| SheetError::BadNumber {
+                    line: name,
+                    value: value.to_string(),
+                })?);
+                digits.push_str(value);
+            }
+            Ok((numbers, digits))
+        };
+
+        let (times, time_digits) = parse_line("Time")?;
+        let (distances, distance_digits) = parse_line("Distance")?;
+        if times.len() != distances.len() {
+            return Err(SheetError::ColumnMismatch {
+                times: times.len(),
+                distances: distances.len(),
+            });
+        } else if times.is_empty() {
+            return Err(SheetError::Empty);
+        }
 
-    println!("{result}");
+        Ok(Self {
+            races: times
+                .into_iter()
+                .zip(distances)
+                .map(|(time, record)| Race { time, record })
+                .collect(),
+            time_digits,
+            distance_digits,
+        })
+    }
 }
 
-fn p1() {
-    let input = std::fs::read_to_string("input").unwrap();
-    let mut data = input.lines();
-    let times = data
-        .next()
-        .unwrap()
-        .split(":")
+fn main() {
+    let model = std::env::args()
         .nth(1)
-        .unwrap()
-        .trim()
-        .split_ascii_whitespace()
-        .map(|x| x.parse::<i64>().unwrap());
+        .map(|name| parse_model(&name))
+        .unwrap_or(Box::new(Linear));
 
-    let distances = data
-        .next()
-        .unwrap()
-        .split(":")
-        .nth(1)
+    let sheet: RaceSheet = std::fs::read_to_string("input")
         .unwrap()
-        .trim()
-        .split_ascii_whitespace()
-        .map(|x| x.parse::<i64>().unwrap());
-
-    let result1: i64 = times
-        .zip(distances)
-        .map(|(max_time, max_distance)| {
-            let mut num_ways = 0;
-            for time_charged in 1..max_time {
-                let time_remaining = max_time - time_charged;
-                let speed = time_charged;
-                let total_distance = speed * time_remaining;
-                if total_distance > max_distance {
-                    num_ways += 1;
-                }
-            }
+        .parse()
+        .unwrap_or_else(|e| panic!("{e}"));
 
-            num_ways
+    let result1 = sheet
+        .races()
+        .iter()
+        .try_fold(1u128, |product, race| {
+            product.checked_mul(race.ways_to_win(model.as_ref()))
         })
-        .product();
+        .expect("product of the ways to win overflowed");
 
     println!("{result1}");
+
+    let kerned = sheet.kerned_race().unwrap_or_else(|e| panic!("{e}"));
+    let result2 = kerned.ways_to_win(model.as_ref());
+
+    println!("{result2}");
+    println!(
+        "optimal charge time: {}",
+        model.optimal_charge_time(&kerned)
+    );
 }
 
-fn main() {
-    p1();
-    p2();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn races_past_u64() {
+        let sheet: RaceSheet = "Time: 4294967296 9999999999\nDistance: 1 2"
+            .parse()
+            .unwrap();
+        let ways = sheet
+            .races()
+            .iter()
+            .map(|race| race.ways_to_win(&Linear))
+            .collect::<Vec<_>>();
+        assert_eq!(ways, [4294967295, 9999999998]);
+
+        let race = Race {
+            time: u128::MAX,
+            record: u128::MAX - 1,
+        };
+        // t = 1 only ties the record, and every other product overflows past it
+        assert_eq!(race.ways_to_win(&Linear), u128::MAX - 3);
+        assert_eq!(race.ways_to_win(&CappedSpeed { max_speed: 1 }), 0);
+    }
+
+    #[test]
+    fn models_match_brute_force() {
+        let models: Vec<(&str, Box<dyn BoatModel>)> = vec![
+            ("linear", Box::new(Linear)),
+            ("quadratic", Box::new(QuadraticAcceleration)),
+            ("capped:0", Box::new(CappedSpeed { max_speed: 0 })),
+            ("capped:1", Box::new(CappedSpeed { max_speed: 1 })),
+            ("capped:7", Box::new(CappedSpeed { max_speed: 7 })),
+            ("capped:30", Box::new(CappedSpeed { max_speed: 30 })),
+            ("drag:1", Box::new(Drag { deceleration: 1 })),
+            ("drag:2", Box::new(Drag { deceleration: 2 })),
+            ("drag:5", Box::new(Drag { deceleration: 5 })),
+            ("drag:100", Box::new(Drag { deceleration: 100 })),
+        ];
+
+        for (name, model) in &models {
+            for time in 0..60 {
+                let distances = (0..=time)
+                    .map(|t| model.distance(time, t).unwrap())
+                    .collect::<Vec<_>>();
+                let best = *distances.iter().max().unwrap();
+
+                let optimal = model.optimal_charge_time(&Race { time, record: 0 });
+                assert_eq!(distances[optimal as usize], best, "{name}, time {time}");
+
+                for record in 0..=best + 1 {
+                    let race = Race { time, record };
+                    let expected = distances.iter().filter(|d| **d > record).count() as u128;
+                    assert_eq!(
+                        race.ways_to_win(model.as_ref()),
+                        expected,
+                        "{name}, time {time}, record {record}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_sheet() {
+        let sheet = "Time:\nDistance:\n".parse::<RaceSheet>();
+        assert!(matches!(sheet, Err(SheetError::Empty)));
+    }
 }
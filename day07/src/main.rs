@@ -1,241 +1,793 @@
-use std::cmp::Ordering;
-
-fn char_to_idx(ch: char) -> usize {
-    match ch {
-        '2' => 0,
-        '3' => 1,
-        '4' => 2,
-        '5' => 3,
-        '6' => 4,
-        '7' => 5,
-        '8' => 6,
-        '9' => 7,
-        'T' => 8,
-        'J' => 9,
-        'Q' => 10,
-        'K' => 11,
-        'A' => 12,
-        _ => panic!("bad"),
-    }
-}
-
-fn char_to_idx_p2(ch: char) -> usize {
-    match ch {
-        'J' => 0,
-        '2' => 1,
-        '3' => 2,
-        '4' => 3,
-        '5' => 4,
-        '6' => 5,
-        '7' => 6,
-        '8' => 7,
-        '9' => 8,
-        'T' => 9,
-        'Q' => 10,
-        'K' => 11,
-        'A' => 12,
-        _ => panic!("bad"),
-    }
-}
-
-fn get_type(hand: &[u8; 13]) -> HandType {
-    match hand.iter().max().unwrap() {
-        5 => HandType::FiveOfAKind,
-        4 => HandType::FourOfAKind,
-        3 => {
-            // three of a kind or full house
-            if hand.iter().any(|num| *num == 2) {
-                HandType::FullHouse
-            } else {
-                HandType::ThreeOfAKind
-            }
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
+
+use rng::Rng;
+
+#[derive(Debug, Clone)]
+struct HandType {
+    name: String,
+    // sizes of the groups of matching cards needed, largest first; a full house is [3, 2]
+    groups: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+struct Rules {
+    // weakest to strongest, which is also the tie-break order
+    ranks: Vec<char>,
+    wild: Vec<char>,
+    hand_size: usize,
+    // strongest first; the last type has no groups so that every hand is something
+    ladder: Vec<HandType>,
+}
+impl Rules {
+    fn new(ranks: &str, wild: &str, hand_size: usize, ladder: &[(&str, &[u8])]) -> Self {
+        Self {
+            ranks: ranks.chars().collect(),
+            wild: wild.chars().collect(),
+            hand_size,
+            ladder: ladder
+                .iter()
+                .map(|(name, groups)| HandType {
+                    name: name.to_string(),
+                    groups: groups.to_vec(),
+                })
+                .collect(),
+        }
+    }
+
+    const CAMEL_LADDER: &'static [(&'static str, &'static [u8])] = &[
+        ("five of a kind", &[5]),
+        ("four of a kind", &[4]),
+        ("full house", &[3, 2]),
+        ("three of a kind", &[3]),
+        ("two pair", &[2, 2]),
+        ("one pair", &[2]),
+        ("high card", &[]),
+    ];
+
+    fn standard() -> Self {
+        Self::new("23456789TJQKA", "", 5, Self::CAMEL_LADDER)
+    }
+
+    fn jokers_wild() -> Self {
+        Self::new("J23456789TQKA", "J", 5, Self::CAMEL_LADDER)
+    }
+
+    fn rank_of(&self, card: char) -> Option<usize> {
+        self.ranks.iter().position(|rank| *rank == card)
+    }
+
+    fn hand(&self, s: &str) -> Result<Hand, HandError> {
+        let cards = s
+            .chars()
+            .map(|card| self.rank_of(card).ok_or(HandError::UnknownCard(card)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if cards.len() != self.hand_size {
+            return Err(HandError::WrongSize {
+                expected: self.hand_size,
+                found: cards.len(),
+            });
         }
-        2 => {
-            // one or two pair
-            if hand.iter().filter(|num| **num == 2).count() == 2 {
-                HandType::TwoPair
+
+        Ok(self.hand_from_ranks(cards))
+    }
+
+    fn hand_from_ranks(&self, cards: Vec<usize>) -> Hand {
+        let mut counts = vec![0u8; self.ranks.len()];
+        let mut wilds = 0;
+        for card in &cards {
+            if self.wild.contains(&self.ranks[*card]) {
+                wilds += 1;
             } else {
-                HandType::OnePair
+                counts[*card] += 1;
             }
         }
-        1 | 0 => HandType::HighCard,
-        _ => panic!("{hand:#?}"),
+
+        let (hand_type, resolved) = self.classify(&counts, wilds);
+        Hand {
+            key: self.sort_key(hand_type, &cards),
+            cards,
+            resolved,
+            hand_type,
+        }
     }
-}
 
-fn score_hand(hand: &[u8; 13], orig_hand: &[usize; 5]) -> u64 {
-    if hand.iter().sum::<u8>() != 5 {
-        return 0;
+    fn card_bits(&self) -> u32 {
+        usize::BITS - (self.ranks.len() - 1).leading_zeros()
     }
 
-    let mut score = (7 - get_type(hand) as u64) * 1_00_00_00_00_00;
+    fn key_bits(&self) -> u32 {
+        let type_bits = usize::BITS - (self.ladder.len() - 1).leading_zeros();
+        type_bits + self.card_bits() * self.hand_size as u32
+    }
 
-    let mut multiplier = 1u64;
-    for card in orig_hand.iter().rev() {
-        score += *card as u64 * multiplier;
-        multiplier *= 100;
+    // the type's strength followed by each card's rank, so that comparing keys compares hands
+    fn sort_key(&self, hand_type: usize, cards: &[usize]) -> u128 {
+        let strength = (self.ladder.len() - 1 - hand_type) as u128;
+        cards.iter().fold(strength, |key, card| {
+            (key << self.card_bits()) | *card as u128
+        })
     }
 
-    score
-}
+    // Finds the strongest hand type the cards can make, and the counts per rank once the wild
+    // cards have been turned into whatever gets there.
+    fn classify(&self, counts: &[u8], mut wilds: u8) -> (usize, Vec<u8>) {
+        // biggest groups first, and the stronger rank when they tie
+        let mut by_count: Vec<usize> = (0..counts.len()).filter(|r| counts[*r] > 0).collect();
+        by_count.sort_by_key(|rank| (counts[*rank], *rank));
+        by_count.reverse();
+
+        // pairing the largest groups needed with the largest groups held always wastes the
+        // fewest wild cards
+        let needed = |groups: &[u8]| -> u8 {
+            groups
+                .iter()
+                .enumerate()
+                .map(|(i, size)| size.saturating_sub(by_count.get(i).map_or(0, |r| counts[*r])))
+                .sum()
+        };
+        let hand_type = self
+            .ladder
+            .iter()
+            .position(|hand_type| needed(&hand_type.groups) <= wilds)
+            .expect("the weakest hand type should match anything");
 
-fn recurse_fill_hand(
-    orig_hand: &[usize; 5],
-    starting_hand: &[u8; 13],
-    (max_score, max_hand): (&mut u64, &mut [u8; 13]),
-) {
-    // dbg!(starting_hand);
-    let non_wilds_in_hand: u8 = starting_hand.iter().sum();
-    // base case
-    if non_wilds_in_hand >= 5 {
-        return;
-    } else if non_wilds_in_hand == 0 {
-        // special case: all wilds
-        *max_hand = [0; 13];
-        max_hand[12] = 5;
-        *max_score = score_hand(&max_hand, &orig_hand);
-        return;
+        // make the groups for real, starting new ones from the strongest unused ranks
+        let mut resolved = counts.to_vec();
+        let mut unused = (0..self.ranks.len())
+            .rev()
+            .filter(|rank| counts[*rank] == 0 && !self.wild.contains(&self.ranks[*rank]));
+        let mut targets = Vec::new();
+        for (i, size) in self.ladder[hand_type].groups.iter().enumerate() {
+            let Some(rank) = by_count.get(i).cloned().or_else(|| unused.next()) else {
+                break;
+            };
+            let added = size.saturating_sub(resolved[rank]);
+            resolved[rank] += added;
+            wilds -= added;
+            targets.push(rank);
+        }
+
+        // anything left over joins the biggest group, which can't make a stronger hand or it
+        // would have matched above
+        if wilds > 0 {
+            let rank = targets
+                .first()
+                .or(by_count.first())
+                .cloned()
+                .or_else(|| unused.next())
+                .unwrap_or(self.ranks.len() - 1);
+            resolved[rank] += wilds;
+        }
+
+        (hand_type, resolved)
     }
 
-    // Recurse on all possible placements
-    // All possible placements are slots that already have a card
-    // except in the all-wilds case, which is handled above
-    for possible_idx in 0..starting_hand.len() {
-        if starting_hand[possible_idx] != 0 {
-            let mut cloned_hand = starting_hand.clone();
-            cloned_hand[possible_idx] += 1;
+    // what each wild card in the hand was turned into, in the order they were dealt
+    fn substitutions(&self, hand: &Hand) -> Vec<(char, char)> {
+        let is_wild = |card: &usize| self.wild.contains(&self.ranks[*card]);
 
-            let score = score_hand(&cloned_hand, orig_hand);
-            if score > *max_score {
-                *max_score = score;
-                *max_hand = cloned_hand;
+        let mut added = hand.resolved.clone();
+        for card in hand.cards.iter().filter(|card| !is_wild(card)) {
+            added[*card] -= 1;
+        }
+
+        let mut targets = (0..added.len())
+            .rev()
+            .flat_map(|rank| std::iter::repeat_n(rank, added[rank] as usize));
+        hand.cards
+            .iter()
+            .filter(|card| is_wild(card))
+            .map(|wild| (self.ranks[*wild], self.ranks[targets.next().unwrap()]))
+            .collect()
+    }
+}
+impl FromStr for Rules {
+    type Err = String;
+
+    // lines of "ranks: ...", "wild: ...", "hand size: N", and "type <name>: <group sizes>" from
+    // the strongest type to the weakest
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ranks = Vec::new();
+        let mut wild = Vec::new();
+        let mut hand_size = None;
+        let mut ladder = Vec::new();
+
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| format!("expected \"key: value\", got {line:?}"))?;
+            let value = value.trim();
+
+            match key.trim() {
+                "ranks" => ranks = value.chars().collect(),
+                "wild" => wild = value.chars().collect(),
+                "hand size" => {
+                    hand_size = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("bad hand size {value}"))?,
+                    )
+                }
+                key => {
+                    let name = key
+                        .strip_prefix("type ")
+                        .ok_or_else(|| format!("unknown key {key:?}"))?;
+                    let mut groups = value
+                        .split_ascii_whitespace()
+                        .map(|size| size.parse().map_err(|_| format!("bad group size {size}")))
+                        .collect::<Result<Vec<u8>, _>>()?;
+                    groups.sort();
+                    groups.reverse();
+                    ladder.push(HandType {
+                        name: name.to_string(),
+                        groups,
+                    });
+                }
             }
+        }
 
-            recurse_fill_hand(orig_hand, &cloned_hand, (max_score, max_hand));
+        let hand_size = hand_size.ok_or("missing hand size")?;
+        // cards are counted in u8s
+        if hand_size > u8::MAX as usize {
+            return Err(format!("hand size {hand_size} is more than {}", u8::MAX));
+        }
+        if ranks.is_empty() {
+            return Err("missing ranks".to_string());
+        }
+        if let Some(card) = wild.iter().find(|card| !ranks.contains(card)) {
+            return Err(format!("wild card {card} is not one of the ranks"));
+        }
+        if ladder
+            .last()
+            .is_none_or(|weakest| !weakest.groups.is_empty())
+        {
+            return Err("the last hand type must need no groups".to_string());
+        }
+        if let Some(hand_type) = ladder.iter().find(|hand_type| {
+            hand_type
+                .groups
+                .iter()
+                .map(|size| *size as usize)
+                .sum::<usize>()
+                > hand_size
+        }) {
+            return Err(format!(
+                "{} needs more than {hand_size} cards",
+                hand_type.name
+            ));
+        }
+
+        let rules = Self {
+            ranks,
+            wild,
+            hand_size,
+            ladder,
+        };
+        if rules.key_bits() > u128::BITS {
+            return Err("too many cards in a hand to build sort keys".to_string());
+        }
+
+        Ok(rules)
+    }
+}
+
+#[derive(Debug)]
+enum HandError {
+    UnknownCard(char),
+    WrongSize { expected: usize, found: usize },
+}
+impl Display for HandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HandError::UnknownCard(card) => write!(f, "unknown card {card:?}"),
+            HandError::WrongSize { expected, found } => {
+                write!(f, "expected {expected} cards, found {found}")
+            }
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct Hand1 {
-    ranks: [u8; 13], // in order of strength, low strength to high
-    orig_hand: [usize; 5],
+struct Hand {
+    // ranks in the order they were dealt
+    cards: Vec<usize>,
+    // count of each rank once wild cards have been substituted
+    resolved: Vec<u8>,
+    // index into the ladder, 0 being the strongest
+    hand_type: usize,
+    key: u128,
 }
-impl Hand1 {
-    fn new(hand_chars: impl Iterator<Item = char>) -> Self {
-        let mut ranks = [0; 13];
-        let mut orig_hand = [100; 5];
 
-        for (i, card) in hand_chars.enumerate() {
-            let idx = char_to_idx(card);
-            orig_hand[i] = idx;
-            ranks[idx] += 1;
+// stronger hands are greater; only meaningful between hands made with the same rules
+impl PartialOrd for Hand {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Hand {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+fn parse_hands(rules: &Rules, input: &str) -> Vec<(Hand, u32)> {
+    input
+        .lines()
+        .map(|line| {
+            let (hand, bid) = line.split_once(' ').unwrap();
+            let hand = rules.hand(hand).unwrap_or_else(|e| panic!("{hand}: {e}"));
+            (hand, bid.trim().parse().unwrap())
+        })
+        .collect()
+}
+
+// LSD radix sort on the packed keys, a byte at a time
+fn radix_sort(items: &mut Vec<(u128, u32)>, key_bits: u32) {
+    let mut buffer = vec![(0, 0); items.len()];
+
+    for shift in (0..key_bits).step_by(8) {
+        let digit = |(key, _): &(u128, u32)| ((key >> shift) & 0xff) as usize;
+
+        let mut starts = [0usize; 256];
+        for item in items.iter() {
+            starts[digit(item)] += 1;
+        }
+        let mut total = 0;
+        for start in starts.iter_mut() {
+            (*start, total) = (total, total + *start);
         }
 
-        assert_eq!(ranks.iter().cloned().sum::<u8>(), 5);
-        Self { ranks, orig_hand }
+        for item in items.iter() {
+            let slot = &mut starts[digit(item)];
+            buffer[*slot] = *item;
+            *slot += 1;
+        }
+        std::mem::swap(items, &mut buffer);
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct Hand2 {
-    orig_hand_p2: [usize; 5],
-    max_hand: [u8; 13],
+fn total_winnings(rules: &Rules, input: &str) -> u64 {
+    let mut keys_and_bids: Vec<(u128, u32)> = parse_hands(rules, input)
+        .into_iter()
+        .map(|(hand, bid)| (hand.key, bid))
+        .collect();
+
+    radix_sort(&mut keys_and_bids, rules.key_bits());
+
+    keys_and_bids
+        .iter()
+        .enumerate()
+        .map(|(idx, (_, bid))| (idx as u64 + 1) * *bid as u64)
+        .sum()
 }
-impl Hand2 {
-    fn new(hand_chars: impl Iterator<Item = char>) -> Self {
-        let mut ranks_p2 = [0; 13];
-        let mut orig_hand_p2 = [101; 5];
 
-        for (i, card) in hand_chars.enumerate() {
-            let idx2 = char_to_idx_p2(card);
-            orig_hand_p2[i] = idx2;
-            ranks_p2[idx2] += 1;
+// Prints how each set of rules sees a hand, and where it would rank among the input's hands
+fn explain(hand_str: &str, rule_sets: &[(String, Rules)], input: Option<&str>) {
+    println!("{hand_str}");
+
+    for (name, rules) in rule_sets {
+        println!("  {name}:");
+
+        let hand = match rules.hand(hand_str) {
+            Ok(hand) => hand,
+            Err(e) => {
+                println!("    not a valid hand: {e}");
+                continue;
+            }
+        };
+
+        let ranks = hand
+            .cards
+            .iter()
+            .map(|card| format!("{}={card}", rules.ranks[*card]))
+            .collect::<Vec<_>>();
+        println!("    ranks (weakest is 0): {}", ranks.join(" "));
+        println!("    type: {}", rules.ladder[hand.hand_type].name);
+
+        let substitutions = rules.substitutions(&hand);
+        if substitutions.is_empty() {
+            println!("    wild cards: none");
+        } else {
+            let substitutions = substitutions
+                .iter()
+                .map(|(wild, rank)| format!("{wild}->{rank}"))
+                .collect::<Vec<_>>();
+            println!("    wild cards: {}", substitutions.join(" "));
         }
 
-        ranks_p2[0] = 0;
-        let mut max_score = score_hand(&ranks_p2, &orig_hand_p2);
-        let mut max_hand = ranks_p2.clone();
-        recurse_fill_hand(&orig_hand_p2, &ranks_p2, (&mut max_score, &mut max_hand));
+        println!("    tie-break key: {:?}", hand.cards);
+        println!("    sort key: {:#x}", hand.key);
+
+        if let Some(input) = input {
+            let hands = parse_hands(rules, input);
+            let rank = hands.iter().filter(|(other, _)| *other < hand).count() + 1;
+            if hands.iter().any(|(other, _)| other.cards == hand.cards) {
+                println!("    rank: {rank} of {} (1 is the weakest)", hands.len());
+            } else {
+                println!(
+                    "    would rank {rank} among {} (1 is the weakest)",
+                    hands.len() + 1
+                );
+            }
+        }
+    }
+}
 
-        assert!(ranks_p2.iter().cloned().sum::<u8>() <= 5);
+#[derive(Debug, Clone)]
+struct Simulation {
+    trials: u32,
+    opponents: usize,
+    // copies of each rank in the deck, or None to deal every card independently
+    copies: Option<usize>,
+    seed: u64,
+}
+impl Default for Simulation {
+    fn default() -> Self {
         Self {
-            orig_hand_p2,
-            max_hand,
+            trials: 100_000,
+            opponents: 1,
+            copies: None,
+            seed: 0,
         }
     }
 }
+impl Simulation {
+    fn set(&mut self, option: &str) -> Result<(), String> {
+        let (key, value) = option
+            .split_once('=')
+            .ok_or_else(|| format!("expected key=value, got {option}"))?;
+        let bad_value = || format!("bad value for {key}: {value}");
 
-impl PartialOrd for Hand1 {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match get_type(&self.ranks).cmp(&get_type(&other.ranks)) {
-            Ordering::Equal => Some(self.orig_hand.cmp(&other.orig_hand).reverse()),
-            ord => Some(ord),
+        match key {
+            "trials" => self.trials = value.parse().map_err(|_| bad_value())?,
+            "opponents" => self.opponents = value.parse().map_err(|_| bad_value())?,
+            "copies" if value == "infinite" => self.copies = None,
+            "copies" => self.copies = Some(value.parse().map_err(|_| bad_value())?),
+            "seed" => self.seed = value.parse().map_err(|_| bad_value())?,
+            _ => return Err(format!("unknown option {key}")),
+        }
+        Ok(())
+    }
+
+    // Deals random opponents against the hand and counts how often it beats all of them,
+    // ties the best of them, or loses to one
+    fn run(&self, rules: &Rules, hand: &Hand) -> Result<Outcome, String> {
+        if self.opponents == 0 || self.trials == 0 {
+            return Err("needs at least one opponent and one trial".to_string());
+        }
+
+        let mut rng = Rng::new(self.seed);
+        let dealt = self.opponents * rules.hand_size;
+
+        // what's left of a finite deck once our hand is out of it
+        let mut deck = Vec::new();
+        if let Some(copies) = self.copies {
+            let mut remaining = vec![copies; rules.ranks.len()];
+            for card in &hand.cards {
+                remaining[*card] = remaining[*card]
+                    .checked_sub(1)
+                    .ok_or_else(|| format!("the deck only has {copies} of each card"))?;
+            }
+            deck = (0..rules.ranks.len())
+                .flat_map(|rank| std::iter::repeat_n(rank, remaining[rank]))
+                .collect();
+            if deck.len() < dealt {
+                return Err(format!(
+                    "not enough cards left for {} opponents",
+                    self.opponents
+                ));
+            }
+        }
+
+        let mut outcome = Outcome::default();
+        for _ in 0..self.trials {
+            let cards: Vec<usize> = if self.copies.is_some() {
+                // partial Fisher-Yates shuffle of just the cards we need
+                for i in 0..dealt {
+                    let j = i + rng.below(deck.len() - i);
+                    deck.swap(i, j);
+                }
+                deck[..dealt].to_vec()
+            } else {
+                (0..dealt).map(|_| rng.below(rules.ranks.len())).collect()
+            };
+
+            let best = cards
+                .chunks_exact(rules.hand_size)
+                .map(|cards| rules.hand_from_ranks(cards.to_vec()))
+                .max()
+                .unwrap();
+            match hand.cmp(&best) {
+                Ordering::Greater => outcome.wins += 1,
+                Ordering::Equal => outcome.ties += 1,
+                Ordering::Less => outcome.losses += 1,
+            }
         }
+
+        Ok(outcome)
     }
 }
-impl Ord for Hand1 {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+
+#[derive(Debug, Default)]
+struct Outcome {
+    wins: u32,
+    ties: u32,
+    losses: u32,
+}
+impl Outcome {
+    // the observed rate with a 95% Wilson score interval
+    fn rate(&self, count: u32) -> (f64, f64, f64) {
+        let n = (self.wins + self.ties + self.losses) as f64;
+        let p = count as f64 / n;
+        let z = 1.96f64;
+
+        let centre = (p + z * z / (2.0 * n)) / (1.0 + z * z / n);
+        let margin = z / (1.0 + z * z / n) * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt();
+        // rounding can push the bounds just past 0 or 1, which would print as -0.00%
+        let clamp = |bound: f64| if bound <= 0.0 { 0.0 } else { bound.min(1.0) };
+        (p, clamp(centre - margin), clamp(centre + margin))
+    }
+}
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts = [
+            ("win", self.wins),
+            ("tie", self.ties),
+            ("lose", self.losses),
+        ]
+        .map(|(name, count)| {
+            let (p, lo, hi) = self.rate(count);
+            format!(
+                "{name} {:.2}% ({:.2}-{:.2}%)",
+                p * 100.0,
+                lo * 100.0,
+                hi * 100.0
+            )
+        });
+        write!(f, "{}", parts.join(", "))
     }
 }
 
-impl PartialOrd for Hand2 {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match get_type(&self.max_hand).cmp(&get_type(&other.max_hand)) {
-            Ordering::Equal => Some(self.orig_hand_p2.cmp(&other.orig_hand_p2).reverse()),
-            ord => Some(ord),
+fn simulate(hand_str: &str, rule_sets: &[(String, Rules)], simulation: &Simulation) {
+    println!(
+        "{hand_str} against {} opponent(s), {} deals, seed {}",
+        simulation.opponents, simulation.trials, simulation.seed
+    );
+
+    for (name, rules) in rule_sets {
+        let result = rules
+            .hand(hand_str)
+            .map_err(|e| format!("not a valid hand: {e}"))
+            .and_then(|hand| simulation.run(rules, &hand));
+        match result {
+            Ok(outcome) => println!("  {name}: {outcome}"),
+            Err(e) => println!("  {name}: {e}"),
         }
     }
 }
-impl Ord for Hand2 {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
-    }
+
+fn load_rules(path: String) -> (String, Rules) {
+    let rules = std::fs::read_to_string(&path)
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|e| panic!("{path}: {e}"));
+    (path, rules)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-enum HandType {
-    FiveOfAKind,  // 7
-    FourOfAKind,  // 6
-    FullHouse,    // 5
-    ThreeOfAKind, // 4
-    TwoPair,      // 3
-    OnePair,      // 2
-    HighCard,     // 1
+enum Command {
+    Score,
+    Explain(String),
+    Simulate(String, Simulation),
 }
 
 fn main() {
-    let mut hands_and_bids: Vec<(Hand1, Hand2, u32)> = std::fs::read_to_string("input")
-        .unwrap()
-        .lines()
-        .map(|line| {
-            let (hand, bid) = line.split_at(line.find(' ').unwrap());
-            let hand1 = Hand1::new(hand.chars());
-            let hand2 = Hand2::new(hand.chars());
-            (hand1, hand2, bid.trim().parse().unwrap())
-        })
-        .collect();
+    let mut args = std::env::args().skip(1).peekable();
 
-    hands_and_bids.sort_by(|(h11, _, _), (h12, _, _)| h11.cmp(h12));
+    // "explain <hand>" describes a single hand, and "simulate <hand> [key=value...]" deals it
+    // against random opponents
+    let command = match args.peek().map(String::as_str) {
+        Some("explain") => Command::Explain(args.nth(1).expect("explain needs a hand")),
+        Some("simulate") => {
+            let hand = args.nth(1).expect("simulate needs a hand");
+            let mut simulation = Simulation::default();
+            while let Some(option) = args.next_if(|arg| arg.contains('=')) {
+                simulation.set(&option).unwrap_or_else(|e| panic!("{e}"));
+            }
+            Command::Simulate(hand, simulation)
+        }
+        _ => Command::Score,
+    };
 
-    let result1: u32 = hands_and_bids
-        .iter()
-        .rev()
-        .enumerate()
-        .map(|(idx, (_, _, bid))| (idx as u32 + 1) * bid)
-        .sum();
+    // any other arguments are files describing variant rules to use instead
+    let mut rule_sets: Vec<(String, Rules)> = args.map(load_rules).collect();
+    if rule_sets.is_empty() {
+        rule_sets = vec![
+            ("standard".to_string(), Rules::standard()),
+            ("jokers wild".to_string(), Rules::jokers_wild()),
+        ];
+    }
 
-    println!("{result1}");
+    match command {
+        Command::Score => {
+            let input = std::fs::read_to_string("input").unwrap();
+            for (_, rules) in &rule_sets {
+                println!("{}", total_winnings(rules, &input));
+            }
+        }
+        Command::Explain(hand) => {
+            let input = std::fs::read_to_string("input").ok();
+            explain(&hand, &rule_sets, input.as_deref());
+        }
+        Command::Simulate(hand, simulation) => simulate(&hand, &rule_sets, &simulation),
+    }
+}
 
-    hands_and_bids.sort_by(|(_, h21, _), (_, h22, _)| h21.cmp(h22));
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let result2: u32 = hands_and_bids
-        .iter()
-        .rev()
-        .enumerate()
-        .map(|(idx, (_, _, bid))| (idx as u32 + 1) * bid)
-        .sum();
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    enum HandType {
+        FiveOfAKind,
+        FourOfAKind,
+        FullHouse,
+        ThreeOfAKind,
+        TwoPair,
+        OnePair,
+        HighCard,
+    }
+
+    fn get_type(hand: &[u8; 13]) -> HandType {
+        match hand.iter().max().unwrap() {
+            5 => HandType::FiveOfAKind,
+            4 => HandType::FourOfAKind,
+            3 => {
+                // three of a kind or full house
+                if hand.contains(&2) {
+                    HandType::FullHouse
+                } else {
+                    HandType::ThreeOfAKind
+                }
+            }
+            2 => {
+                // one or two pair
+                if hand.iter().filter(|num| **num == 2).count() == 2 {
+                    HandType::TwoPair
+                } else {
+                    HandType::OnePair
+                }
+            }
+            1 | 0 => HandType::HighCard,
+            _ => panic!("{hand:#?}"),
+        }
+    }
+
+    fn score_hand(hand: &[u8; 13], orig_hand: &[usize; 5]) -> u64 {
+        if hand.iter().sum::<u8>() != 5 {
+            return 0;
+        }
+
+        let mut score = (7 - get_type(hand) as u64) * 1_00_00_00_00_00;
+
+        let mut multiplier = 1u64;
+        for card in orig_hand.iter().rev() {
+            score += *card as u64 * multiplier;
+            multiplier *= 100;
+        }
+
+        score
+    }
+
+    // the original search: try every placement of every joker and keep the best scoring hand
+    fn recurse_fill_hand(
+        orig_hand: &[usize; 5],
+        starting_hand: &[u8; 13],
+        (max_score, max_hand): (&mut u64, &mut [u8; 13]),
+    ) {
+        let non_wilds_in_hand: u8 = starting_hand.iter().sum();
+        // base case
+        if non_wilds_in_hand >= 5 {
+            return;
+        } else if non_wilds_in_hand == 0 {
+            // special case: all wilds
+            *max_hand = [0; 13];
+            max_hand[12] = 5;
+            *max_score = score_hand(max_hand, orig_hand);
+            return;
+        }
+
+        // All possible placements are slots that already have a card
+        // except in the all-wilds case, which is handled above
+        for (possible_idx, count) in starting_hand.iter().enumerate() {
+            if *count != 0 {
+                let mut cloned_hand = *starting_hand;
+                cloned_hand[possible_idx] += 1;
+
+                let score = score_hand(&cloned_hand, orig_hand);
+                if score > *max_score {
+                    *max_score = score;
+                    *max_hand = cloned_hand;
+                }
 
-    println!("{result2}");
+                recurse_fill_hand(orig_hand, &cloned_hand, (max_score, max_hand));
+            }
+        }
+    }
+
+    #[test]
+    fn seeded_simulation_is_reproducible() {
+        let rules = Rules::standard();
+        let hand = rules.hand("32T3K").unwrap();
+
+        let mut simulation = Simulation {
+            trials: 1000,
+            seed: 42,
+            ..Simulation::default()
+        };
+        let counts = |simulation: &Simulation| {
+            let outcome = simulation.run(&rules, &hand).unwrap();
+            (outcome.wins, outcome.ties, outcome.losses)
+        };
+        assert_eq!(counts(&simulation), (453, 0, 547));
+        assert_eq!(counts(&simulation), (453, 0, 547));
+
+        simulation.copies = Some(4);
+        simulation.opponents = 3;
+        assert_eq!(counts(&simulation), (154, 0, 846));
+    }
+
+    #[test]
+    fn rate_bounds_stay_in_range() {
+        let outcome = Outcome {
+            wins: 0,
+            ties: 0,
+            losses: 10,
+        };
+        assert_eq!(outcome.rate(0).1, 0.0);
+        assert_eq!(outcome.rate(10).2, 1.0);
+        assert!(!outcome.to_string().contains("-0.00"), "{outcome}");
+    }
+
+    #[test]
+    fn oversized_hand_rejected() {
+        let rules = "ranks: A\nhand size: 300\ntype high:\n".parse::<Rules>();
+        assert!(rules.is_err());
+        let rules = "ranks: A\nhand size: 255\ntype high:\n".parse::<Rules>();
+        assert!(rules.is_ok());
+    }
+
+    // ranks are indexed the same way as Rules::jokers_wild, with J as 0
+    fn recursive_best(cards: &[usize; 5]) -> [u8; 13] {
+        let mut ranks = [0; 13];
+        for card in cards {
+            ranks[*card] += 1;
+        }
+        ranks[0] = 0;
+
+        let mut max_score = score_hand(&ranks, cards);
+        let mut max_hand = ranks;
+        recurse_fill_hand(cards, &ranks, (&mut max_score, &mut max_hand));
+        max_hand
+    }
+
+    #[test]
+    fn jokers_match_recursive_search() {
+        let rules = Rules::jokers_wild();
+        for i in 0..13usize.pow(5) {
+            let cards: [usize; 5] = std::array::from_fn(|j| i / 13usize.pow(j as u32) % 13);
+            let hand_str = cards
+                .iter()
+                .map(|card| rules.ranks[*card])
+                .collect::<String>();
+            let hand = rules.hand(&hand_str).unwrap();
+
+            let expected = get_type(&recursive_best(&cards)) as usize;
+            assert_eq!(hand.hand_type, expected, "{hand_str}");
+
+            // the resolved counts have to actually make that type
+            let resolved: [u8; 13] = hand.resolved.clone().try_into().unwrap();
+            assert_eq!(resolved.iter().sum::<u8>(), 5, "{hand_str}");
+            assert_eq!(get_type(&resolved) as usize, expected, "{hand_str}");
+        }
+    }
 }
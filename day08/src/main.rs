@@ -1,119 +1,589 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::Infallible,
+    fmt::{Display, Write as _},
+    str::FromStr,
+};
 
+#[derive(Debug, Clone, Copy)]
 enum Move {
     Left,
     Right,
 }
 
-fn lcm(nums: &[u64]) -> u64 {
-    if nums.len() == 1 {
-        nums[0]
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a.abs()
     } else {
-        let others = lcm(&nums[1..]);
-        nums[0] * others / gcd(nums[0], others)
+        gcd(b, a % b)
     }
 }
-fn gcd(a: u64, b: u64) -> u64 {
+
+// (g, x, y) with a*x + b*y = g
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
     if b == 0 {
-        a
+        (a, 1, 0)
     } else {
-        gcd(b, a % b)
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
     }
 }
 
-fn main() {
-    let inp = std::fs::read_to_string("input").unwrap();
-    let mut parts = inp.split("\n\n");
-    let mut moves = parts
-        .next()
-        .unwrap()
-        .trim()
-        .chars()
-        .map(|c| match c {
-            'L' => Move::Left,
-            'R' => Move::Right,
-            _ => panic!(),
-        })
-        .cycle();
-
-    let mut name_to_id = HashMap::new();
-    let mut unresolved_lines = Vec::new();
-    let mut start_id = None;
-    let mut ghost_ids = Vec::new();
-    let mut goal_id = None;
-    let mut ghost_goals = HashSet::new();
-    for (id, line) in parts.next().unwrap().lines().enumerate() {
-        let (name, neighbors) = line.split_at(line.find(" = ").unwrap());
-        let neighbors = neighbors
-            .strip_prefix(" = (")
-            .unwrap()
-            .strip_suffix(")")
-            .unwrap();
-        let (left, right) = neighbors.split_at(neighbors.find(", ").unwrap());
-        let right = right.strip_prefix(", ").unwrap();
+// Combines x = a1 (mod m1) and x = a2 (mod m2) into a single congruence, if there is one.
+// The moduli don't need to be coprime.
+fn crt((a1, m1): (i128, i128), (a2, m2): (i128, i128)) -> Option<(i128, i128)> {
+    let g = gcd(m1, m2);
+    if (a2 - a1) % g != 0 {
+        return None;
+    }
 
-        name_to_id.insert(name.to_string(), id);
-        unresolved_lines.push((left, right));
-        assert_eq!(id, unresolved_lines.len() - 1);
+    let (_, inv, _) = extended_gcd(m1 / g, m2 / g);
+    let lcm = m1 / g * m2;
+    let k = ((a2 - a1) / g % (m2 / g) * inv).rem_euclid(m2 / g);
+    Some(((a1 + m1 * k).rem_euclid(lcm), lcm))
+}
 
-        if name == "AAA" {
-            start_id = Some(id);
+#[derive(Debug)]
+enum NetworkError {
+    NoInstructions,
+    MissingNodes,
+    BadInstruction(char),
+    BadLine(String),
+    DuplicateNode(String),
+    DanglingReference { node: String, missing: String },
+    UnknownNode(String),
+    NoMatches(String),
+}
+impl Display for NetworkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetworkError::NoInstructions => write!(f, "no instructions"),
+            NetworkError::MissingNodes => write!(f, "missing the list of nodes"),
+            NetworkError::BadInstruction(c) => write!(f, "bad instruction {c:?}"),
+            NetworkError::BadLine(line) => write!(f, "bad node line {line:?}"),
+            NetworkError::DuplicateNode(name) => write!(f, "node {name} is defined twice"),
+            NetworkError::DanglingReference { node, missing } => {
+                write!(f, "node {node} leads to {missing}, which doesn't exist")
+            }
+            NetworkError::UnknownNode(name) => write!(f, "no node named {name}"),
+            NetworkError::NoMatches(selector) => write!(f, "no nodes match {selector}"),
         }
-        if name.ends_with('A') {
-            ghost_ids.push(id);
+    }
+}
+
+// Picks out start or goal nodes, either as a comma separated list of names or as a glob
+// pattern where * matches any run of characters and ? matches any one
+#[derive(Debug, Clone)]
+enum NodeSelector {
+    Names(Vec<String>),
+    Glob(String),
+}
+impl NodeSelector {
+    fn select(&self, network: &Network) -> Result<Vec<usize>, NetworkError> {
+        let selected = match self {
+            NodeSelector::Names(names) => names
+                .iter()
+                .map(|name| {
+                    network
+                        .name_to_id
+                        .get(name)
+                        .cloned()
+                        .ok_or_else(|| NetworkError::UnknownNode(name.clone()))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            NodeSelector::Glob(pattern) => (0..network.names.len())
+                .filter(|id| glob_match(pattern.as_bytes(), network.names[*id].as_bytes()))
+                .collect(),
+        };
+
+        if selected.is_empty() {
+            return Err(NetworkError::NoMatches(self.to_string()));
         }
+        Ok(selected)
+    }
+}
+impl FromStr for NodeSelector {
+    type Err = Infallible;
 
-        if name == "ZZZ" {
-            goal_id = Some(id);
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains(['*', '?']) {
+            Ok(NodeSelector::Glob(s.to_string()))
+        } else {
+            Ok(NodeSelector::Names(
+                s.split(',').map(|name| name.trim().to_string()).collect(),
+            ))
         }
-        if name.ends_with('Z') {
-            ghost_goals.insert(id);
+    }
+}
+impl Display for NodeSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeSelector::Names(names) => write!(f, "{}", names.join(",")),
+            NodeSelector::Glob(pattern) => write!(f, "{pattern}"),
         }
     }
+}
 
-    let mut current_id = start_id.unwrap();
-    let goal_id = goal_id.unwrap();
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, None) => true,
+        (Some((b'*', rest)), _) => {
+            glob_match(rest, name) || (!name.is_empty() && glob_match(pattern, &name[1..]))
+        }
+        (Some((b'?', rest)), Some((_, name_rest))) => glob_match(rest, name_rest),
+        (Some((p, rest)), Some((n, name_rest))) => p == n && glob_match(rest, name_rest),
+        _ => false,
+    }
+}
 
-    let mut left_paths = Vec::new();
-    let mut right_paths = Vec::new();
-    for (left, right) in unresolved_lines {
-        left_paths.push(*name_to_id.get(left).expect("unresolved name"));
-        right_paths.push(*name_to_id.get(right).expect("unresolved name"));
+struct Network {
+    names: Vec<String>,
+    name_to_id: HashMap<String, usize>,
+    moves: Vec<Move>,
+    left_paths: Vec<usize>,
+    right_paths: Vec<usize>,
+}
+impl Network {
+    fn step(&self, node: usize, instruction: usize) -> usize {
+        match self.moves[instruction] {
+            Move::Left => self.left_paths[node],
+            Move::Right => self.right_paths[node],
+        }
     }
 
-    let mut steps = 0u64;
-    let mut normal_path_steps = None;
-    let mut ghost_path_steps = ghost_ids.iter().map(|_| None).collect::<Vec<Option<u64>>>();
-    while normal_path_steps.is_none() || ghost_path_steps.iter().any(|x| x.is_none()) {
-        let next_move = moves.next().unwrap();
-        steps += 1;
+    // Graphviz source for the network, with starts filled green and goals filled red
+    fn to_dot(&self, starts: &[usize], goals: &HashSet<usize>) -> String {
+        let mut dot = String::from("digraph network {\n");
 
-        current_id = match next_move {
-            Move::Left => left_paths[current_id],
-            Move::Right => right_paths[current_id],
-        };
-        if current_id == goal_id && normal_path_steps.is_none() {
-            normal_path_steps = Some(steps);
+        for (id, name) in self.names.iter().enumerate() {
+            let fill = match (starts.contains(&id), goals.contains(&id)) {
+                (true, true) => " style=filled fillcolor=\"palegreen:salmon\"",
+                (true, false) => " style=filled fillcolor=palegreen",
+                (false, true) => " style=filled fillcolor=salmon",
+                (false, false) => "",
+            };
+            writeln!(dot, "    \"{name}\" [label=\"{name}\"{fill}];").unwrap();
         }
 
-        for (ghost_id, ghost_steps) in ghost_ids.iter_mut().zip(ghost_path_steps.iter_mut()) {
-            *ghost_id = match next_move {
-                Move::Left => left_paths[*ghost_id],
-                Move::Right => right_paths[*ghost_id],
+        for (id, name) in self.names.iter().enumerate() {
+            let (left, right) = (
+                &self.names[self.left_paths[id]],
+                &self.names[self.right_paths[id]],
+            );
+            if left == right {
+                writeln!(dot, "    \"{name}\" -> \"{left}\" [label=\"L/R\"];").unwrap();
+            } else {
+                writeln!(dot, "    \"{name}\" -> \"{left}\" [label=\"L\"];").unwrap();
+                writeln!(dot, "    \"{name}\" -> \"{right}\" [label=\"R\"];").unwrap();
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    // Walks the (node, instruction index) states from start until one repeats
+    fn analyse(&self, start: usize, goals: &HashSet<usize>) -> Cycle {
+        let mut first_seen = HashMap::new();
+        // a start on a goal counts as step 0, so a loop back to it keeps the goal
+        let mut goal_steps = Vec::new();
+        if goals.contains(&start) {
+            goal_steps.push(0);
+        }
+        let mut node = start;
+        let mut steps = 0u64;
+
+        loop {
+            let instruction = (steps % self.moves.len() as u64) as usize;
+            if let Some(tail) = first_seen.insert((node, instruction), steps) {
+                // the goal we just reached again was already counted at the start of the loop
+                goal_steps.retain(|t| *t < steps);
+                let (tail_goals, loop_goals) = goal_steps.iter().partition(|t| **t < tail);
+                return Cycle {
+                    tail,
+                    length: steps - tail,
+                    tail_goals,
+                    loop_goals,
+                };
+            }
+
+            node = self.step(node, instruction);
+            steps += 1;
+            if goals.contains(&node) {
+                goal_steps.push(steps);
+            }
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = NetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split("\n\n");
+        let moves: Vec<Move> = parts
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .chars()
+            .map(|c| match c {
+                'L' => Ok(Move::Left),
+                'R' => Ok(Move::Right),
+                _ => Err(NetworkError::BadInstruction(c)),
+            })
+            .collect::<Result<_, _>>()?;
+        if moves.is_empty() {
+            return Err(NetworkError::NoInstructions);
+        }
+
+        let mut names = Vec::new();
+        let mut name_to_id = HashMap::new();
+        let mut unresolved_lines = Vec::new();
+        for (id, line) in parts
+            .next()
+            .ok_or(NetworkError::MissingNodes)?
+            .lines()
+            .enumerate()
+        {
+            let bad_line = || NetworkError::BadLine(line.to_string());
+            let (name, neighbors) = line.split_once(" = ").ok_or_else(bad_line)?;
+            let (left, right) = neighbors
+                .strip_prefix('(')
+                .and_then(|neighbors| neighbors.strip_suffix(')'))
+                .and_then(|neighbors| neighbors.split_once(", "))
+                .ok_or_else(bad_line)?;
+
+            if name_to_id.insert(name.to_string(), id).is_some() {
+                return Err(NetworkError::DuplicateNode(name.to_string()));
+            }
+            names.push(name.to_string());
+            unresolved_lines.push((left, right));
+        }
+
+        let mut left_paths = Vec::new();
+        let mut right_paths = Vec::new();
+        for (id, (left, right)) in unresolved_lines.into_iter().enumerate() {
+            let resolve = |target: &str| {
+                name_to_id
+                    .get(target)
+                    .cloned()
+                    .ok_or_else(|| NetworkError::DanglingReference {
+                        node: names[id].clone(),
+                        missing: target.to_string(),
+                    })
             };
-            if ghost_goals.contains(ghost_id) && ghost_steps.is_none() {
-                *ghost_steps = Some(steps);
+            left_paths.push(resolve(left)?);
+            right_paths.push(resolve(right)?);
+        }
+
+        Ok(Network {
+            names,
+            name_to_id,
+            moves,
+            left_paths,
+            right_paths,
+        })
+    }
+}
+
+// Binary lifting tables over the (node, instruction index) states: where a walker ends up after
+// 2^j steps, and whether it passes through a goal on the way (not counting where it started)
+struct JumpTable {
+    moves_len: usize,
+    // [level][node * moves_len + instruction] -> (state, hit a goal), for up to a pass's worth
+    steps: Vec<Vec<(usize, bool)>>,
+    // [level][node] -> (node, hit a goal), for 2^j whole passes through the instructions
+    passes: Vec<Vec<(usize, bool)>>,
+}
+impl JumpTable {
+    fn new(network: &Network, goals: &HashSet<usize>) -> Self {
+        let moves_len = network.moves.len();
+        let states = network.left_paths.len() * moves_len;
+
+        let single = (0..states)
+            .map(|state| {
+                let (node, instruction) = (state / moves_len, state % moves_len);
+                let next = network.step(node, instruction);
+                (
+                    next * moves_len + (instruction + 1) % moves_len,
+                    goals.contains(&next),
+                )
+            })
+            .collect();
+        // enough levels to skip over anything shorter than a pass
+        let step_levels = usize::BITS - moves_len.leading_zeros();
+        let steps = Self::double(single, step_levels, |(state, _)| state);
+
+        let single_pass = (0..network.left_paths.len())
+            .map(|node| {
+                let (mut state, mut hit) = (node * moves_len, false);
+                for _ in 0..moves_len {
+                    let (next, next_hit) = steps[0][state];
+                    (state, hit) = (next, hit || next_hit);
+                }
+                (state / moves_len, hit)
+            })
+            .collect();
+        // as many as it takes to cover any u64 number of steps
+        let pass_levels = u64::BITS - (u64::MAX / moves_len as u64).leading_zeros();
+        let passes = Self::double(single_pass, pass_levels, |(node, _)| node);
+
+        Self {
+            moves_len,
+            steps,
+            passes,
+        }
+    }
+
+    fn double(
+        first: Vec<(usize, bool)>,
+        levels: u32,
+        index: impl Fn((usize, bool)) -> usize,
+    ) -> Vec<Vec<(usize, bool)>> {
+        let mut table = vec![first];
+        for _ in 1..levels {
+            let last = table.last().unwrap();
+            let next = last
+                .iter()
+                .map(|(mid, hit)| {
+                    let (end, end_hit) = last[index((*mid, *hit))];
+                    (end, *hit || end_hit)
+                })
+                .collect();
+            table.push(next);
+        }
+        table
+    }
+
+    fn position_after(&self, start: usize, steps: u64) -> usize {
+        let (passes, rest) = (steps / self.moves_len as u64, steps % self.moves_len as u64);
+
+        let mut node = start;
+        for (level, table) in self.passes.iter().enumerate() {
+            if passes & (1 << level) != 0 {
+                node = table[node].0;
+            }
+        }
+
+        let mut state = node * self.moves_len;
+        for (level, table) in self.steps.iter().enumerate() {
+            if rest & (1 << level) != 0 {
+                state = table[state].0;
+            }
+        }
+        state / self.moves_len
+    }
+
+    // The first step, no later than limit, on which the walker is at a goal
+    fn first_goal_hit(&self, start: usize, limit: u64) -> Option<u64> {
+        let pass_len = self.moves_len as u64;
+        let mut steps = 0u64;
+
+        // skip as many whole passes as we can without passing a goal
+        let mut node = start;
+        for (level, table) in self.passes.iter().enumerate().rev() {
+            let span = pass_len << level;
+            let (next, hit) = table[node];
+            if !hit && steps.checked_add(span).is_some_and(|end| end <= limit) {
+                node = next;
+                steps += span;
+            }
+        }
+
+        // then single steps, which leaves us just before the goal
+        let mut state = node * self.moves_len;
+        for (level, table) in self.steps.iter().enumerate().rev() {
+            let span = 1 << level;
+            let (next, hit) = table[state];
+            if !hit && steps.checked_add(span).is_some_and(|end| end <= limit) {
+                state = next;
+                steps += span;
             }
         }
+
+        (steps < limit && self.steps[0][state].1).then(|| steps + 1)
+    }
+}
+
+// The path of one walker: after `tail` steps it repeats every `length` steps forever
+#[derive(Debug)]
+struct Cycle {
+    tail: u64,
+    length: u64,
+    // steps on which the walker is at a goal, before and within the first loop
+    tail_goals: Vec<u64>,
+    loop_goals: Vec<u64>,
+}
+impl Cycle {
+    fn at_goal(&self, steps: u64) -> bool {
+        if steps < self.tail {
+            self.tail_goals.contains(&steps)
+        } else {
+            let offset = (steps - self.tail) % self.length;
+            self.loop_goals.contains(&(self.tail + offset))
+        }
+    }
+}
+
+// The first step (after the start) on which every walker is at a goal, or None if that never
+// happens
+fn first_simultaneous_arrival(cycles: &[Cycle]) -> Option<u64> {
+    let last_tail = cycles.iter().map(|cycle| cycle.tail).max()?;
+
+    // before every walker is looping, the one with the longest tail only has a few chances
+    let longest = cycles.iter().find(|cycle| cycle.tail == last_tail).unwrap();
+    if let Some(steps) = longest
+        .tail_goals
+        .iter()
+        .filter(|steps| **steps > 0)
+        .find(|steps| cycles.iter().all(|cycle| cycle.at_goal(**steps)))
+    {
+        return Some(*steps);
+    }
+
+    // after that, every walker is periodic; combine every choice of goal in each loop
+    let mut solutions = vec![(0i128, 1i128)];
+    for cycle in cycles {
+        let length = cycle.length as i128;
+        let mut combined: Vec<_> = solutions
+            .iter()
+            .flat_map(|solution| {
+                cycle
+                    .loop_goals
+                    .iter()
+                    .filter_map(move |steps| crt(*solution, (*steps as i128 % length, length)))
+            })
+            .collect();
+        combined.sort();
+        combined.dedup();
+        solutions = combined;
+    }
+
+    let earliest = last_tail.max(1) as i128;
+    solutions
+        .into_iter()
+        .map(|(residue, modulus)| {
+            let behind = (earliest - residue).max(0);
+            residue + (behind + modulus - 1) / modulus * modulus
+        })
+        .min()
+        .map(|steps| u64::try_from(steps).expect("arrival too far away"))
+}
+
+// The first step on which every walker from starts is at one of goals
+fn solve(network: &Network, starts: &[usize], goals: &HashSet<usize>) -> Option<u64> {
+    if let [start] = starts {
+        return JumpTable::new(network, goals).first_goal_hit(*start, u64::MAX);
     }
 
-    println!("{}", normal_path_steps.unwrap());
+    let cycles = starts
+        .iter()
+        .map(|id| network.analyse(*id, goals))
+        .collect::<Vec<_>>();
+
+    let steps = first_simultaneous_arrival(&cycles)?;
+    let table = JumpTable::new(network, goals);
+    assert!(starts
+        .iter()
+        .all(|id| goals.contains(&table.position_after(*id, steps))));
+    Some(steps)
+}
+
+fn main() {
+    let network: Network = std::fs::read_to_string("input")
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|e| panic!("{e}"));
+
+    // "--start <selector>" and "--goal <selector>" replace the puzzle's starts and goals
+    let mut start_selector = None;
+    let mut goal_selector = None;
+    let mut command = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--start" => start_selector = args.next().map(|arg| arg.parse().unwrap()),
+            "--goal" => goal_selector = args.next().map(|arg| arg.parse().unwrap()),
+            _ => command.push(arg),
+        }
+    }
 
-    let ghost_steps = ghost_path_steps
+    let select =
+        |selector: &NodeSelector| selector.select(&network).unwrap_or_else(|e| panic!("{e}"));
+    let ghost_starts = NodeSelector::Glob("*A".to_string());
+    let ghost_goals = NodeSelector::Glob("*Z".to_string());
+    let starts = select(start_selector.as_ref().unwrap_or(&ghost_starts));
+    let goals: HashSet<usize> = select(goal_selector.as_ref().unwrap_or(&ghost_goals))
         .into_iter()
-        .collect::<Option<Vec<u64>>>()
-        .unwrap();
-    dbg!(&ghost_steps);
-    let result = lcm(&ghost_steps[..]);
-    println!("{result}");
+        .collect();
+
+    match command.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        // jumps straight to where a walker from node ends up
+        ["walk", start, steps] => {
+            let start = select(&NodeSelector::Names(vec![start.to_string()]))[0];
+            let steps: u64 = steps.parse().unwrap();
+
+            let table = JumpTable::new(&network, &goals);
+            println!("{}", network.names[table.position_after(start, steps)]);
+            match table.first_goal_hit(start, steps) {
+                Some(hit) => println!("first reaches a goal after {hit} steps"),
+                None => println!("doesn't reach a goal within {steps} steps"),
+            }
+        }
+        ["dot", path] => std::fs::write(path, network.to_dot(&starts, &goals)).unwrap(),
+        [] if start_selector.is_none() && goal_selector.is_none() => {
+            let start = select(&NodeSelector::Names(vec!["AAA".to_string()]));
+            let goal = select(&NodeSelector::Names(vec!["ZZZ".to_string()]));
+            match solve(&network, &start, &goal.into_iter().collect()) {
+                Some(steps) => println!("{steps}"),
+                None => println!("never reaches ZZZ"),
+            }
+
+            match solve(&network, &starts, &goals) {
+                Some(steps) => println!("{steps}"),
+                None => println!("the ghosts are never all on Z nodes at once"),
+            }
+        }
+        [] => match solve(&network, &starts, &goals) {
+            Some(steps) => println!("{steps}"),
+            None => println!("the walkers are never all at goals at once"),
+        },
+        _ => panic!("usage: [--start <nodes>] [--goal <nodes>] [walk <node> <steps> | dot <file>]"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_instructions() {
+        let network = "\n\nAAA = (AAA, AAA)\n".parse::<Network>();
+        assert!(matches!(network, Err(NetworkError::NoInstructions)));
+    }
+
+    #[test]
+    fn unreachable_goal() {
+        let network: Network = "LR\n\nAAA = (BBB, BBB)\nBBB = (AAA, AAA)\nZZZ = (ZZZ, ZZZ)\n"
+            .parse()
+            .unwrap();
+        let start = network.name_to_id["AAA"];
+        let goals = HashSet::from([network.name_to_id["ZZZ"]]);
+
+        let table = JumpTable::new(&network, &goals);
+        assert_eq!(table.first_goal_hit(start, u64::MAX), None);
+        assert_eq!(solve(&network, &[start], &goals), None);
+    }
+
+    #[test]
+    fn start_on_goal_in_loop() {
+        let network: Network =
+            "L\n\nP = (Q, Q)\nQ = (P, P)\nT = (U, U)\nU = (V, V)\nV = (W, W)\nW = (T, T)\n"
+                .parse()
+                .unwrap();
+        let starts = [network.name_to_id["P"], network.name_to_id["T"]];
+        let goals = HashSet::from([network.name_to_id["P"], network.name_to_id["V"]]);
+
+        assert_eq!(solve(&network, &starts, &goals), Some(2));
+    }
 }
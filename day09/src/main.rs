@@ -1,56 +1,135 @@
+use std::fmt::Display;
+
+use poly::Polynomial;
+
+#[derive(Debug)]
+enum HistoryError {
+    // fewer than two values, so there's nothing to take differences of
+    InsufficientData,
+    // only the polynomial through every value fits, so there's nothing left to check it against
+    NotPolynomial,
+    Overflow,
+}
+impl Display for HistoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistoryError::InsufficientData => write!(f, "not enough values"),
+            HistoryError::NotPolynomial => write!(f, "not a polynomial"),
+            HistoryError::Overflow => write!(f, "differences overflowed"),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct History {
-    derivatives: Vec<Vec<i64>>,
+    len: usize,
+    // fitted with the first value at x = 0
+    polynomial: Polynomial,
 }
 impl History {
-    fn predict(&self) -> i64 {
-        let mut predictions = vec![0i64; self.derivatives.len()];
-        for i in (1..predictions.len()).rev() {
-            predictions[i - 1] = self.derivatives[i - 1].last().unwrap() + predictions[i];
+    fn new(values: &[i64]) -> Result<Self, HistoryError> {
+        if values.len() < 2 {
+            return Err(HistoryError::InsufficientData);
+        }
+
+        let samples = values
+            .iter()
+            .enumerate()
+            .map(|(x, y)| (x as i128, *y as i128))
+            .collect::<Vec<_>>();
+        // the samples are distinct and there's at least one, so overflow is the only way to fail
+        let polynomial = Polynomial::fit(&samples).map_err(|_| HistoryError::Overflow)?;
+        if polynomial.degree() == values.len() - 1 {
+            return Err(HistoryError::NotPolynomial);
         }
 
-        *predictions.first().unwrap()
+        Ok(Self {
+            len: values.len(),
+            polynomial,
+        })
     }
 
-    fn predict_back(&self) -> i64 {
-        let mut predictions = vec![0i64; self.derivatives.len()];
-        for i in (1..predictions.len()).rev() {
-            predictions[i - 1] = self.derivatives[i - 1].first().unwrap() - predictions[i];
-        }
+    // a polynomial through integers at consecutive integers is an integer at every integer,
+    // so this can only fail by overflowing
+    fn value_at(&self, x: i128) -> i128 {
+        self.polynomial
+            .eval_integer(x)
+            .unwrap_or_else(|e| panic!("extrapolation failed: {e}"))
+    }
 
-        *predictions.first().unwrap()
+    // k steps past the last value
+    fn forward(&self, k: i128) -> i128 {
+        self.value_at(self.len as i128 - 1 + k)
+    }
+
+    // k steps before the first value
+    fn backward(&self, k: i128) -> i128 {
+        self.value_at(-k)
+    }
+
+    fn predict(&self) -> i128 {
+        self.forward(1)
+    }
+
+    fn predict_back(&self) -> i128 {
+        self.backward(1)
     }
 }
 
 fn main() {
-    let (pred1, pred2): (Vec<_>, Vec<_>) = std::fs::read_to_string("input")
-        .unwrap()
+    let input = std::fs::read_to_string("input").unwrap();
+    let histories = input
         .lines()
         .map(|line| {
             let values = line
                 .split_ascii_whitespace()
                 .map(|x| x.parse::<i64>().unwrap())
                 .collect::<Vec<i64>>();
-            let mut history = History {
-                derivatives: vec![values],
-            };
+            History::new(&values)
+        })
+        .collect::<Vec<_>>();
 
-            while history.derivatives.last().unwrap().iter().any(|x| *x != 0) {
-                history.derivatives.push(
-                    history
-                        .derivatives
-                        .last()
-                        .unwrap()
-                        .windows(2)
-                        .map(|x| x[1] - x[0])
-                        .collect(),
-                );
-            }
+    // "at <index>", "forward <k>" or "backward <k>" extrapolates every history further
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let [direction, k] = &args[..] {
+        let k: i128 = k.parse().unwrap();
+        let mut total = 0;
+        for (line, history) in histories.iter().enumerate() {
+            let history = match history {
+                Ok(history) => history,
+                Err(e) => {
+                    println!("line {}: {e}, skipping", line + 1);
+                    continue;
+                }
+            };
+            let value = match direction.as_str() {
+                "at" => history.value_at(k),
+                "forward" => history.forward(k),
+                "backward" => history.backward(k),
+                _ => panic!("unknown direction {direction}"),
+            };
+            println!(
+                "degree {}, {}: {value}",
+                history.polynomial.degree(),
+                history.polynomial
+            );
+            total += value;
+        }
+        println!("{total}");
+        return;
+    }
 
+    let (pred1, pred2): (Vec<_>, Vec<_>) = histories
+        .iter()
+        .enumerate()
+        .map(|(line, history)| {
+            let history = history
+                .as_ref()
+                .unwrap_or_else(|e| panic!("line {}: {e}", line + 1));
             (history.predict(), history.predict_back())
         })
         .unzip();
 
-    println!("{}", pred1.into_iter().sum::<i64>());
-    println!("{}", pred2.into_iter().sum::<i64>());
+    println!("{}", pred1.into_iter().sum::<i128>());
+    println!("{}", pred2.into_iter().sum::<i128>());
 }
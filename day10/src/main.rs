@@ -1,3 +1,10 @@
+use std::{
+    fmt::{Display, Write},
+    str::FromStr,
+};
+
+use rng::Rng;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Tile {
     Vertical,
@@ -10,6 +17,29 @@ enum Tile {
     UnkStarting,
 }
 impl Tile {
+    // everything S could stand for
+    const SHAPES: [Tile; 6] = [
+        Tile::Vertical,
+        Tile::Horizontal,
+        Tile::BottomLeft,
+        Tile::BottomRight,
+        Tile::TopLeft,
+        Tile::TopRight,
+    ];
+
+    fn to_char(self) -> char {
+        match self {
+            Tile::Vertical => '|',
+            Tile::Horizontal => '-',
+            Tile::BottomLeft => 'L',
+            Tile::BottomRight => 'J',
+            Tile::TopLeft => 'F',
+            Tile::TopRight => '7',
+            Tile::Nothing => '.',
+            Tile::UnkStarting => 'S',
+        }
+    }
+
     fn can_link(self, direction: Direction, other: Tile) -> bool {
         self.connects_in_direction(direction) && other.connects_in_direction(direction.opposite())
     }
@@ -64,6 +94,41 @@ enum EncloseResult {
     NotEnclosed,
 }
 
+#[derive(Debug, Clone, Copy)]
+struct SvgMarks {
+    start: bool,
+    farthest: bool,
+}
+
+#[derive(Debug)]
+enum MapError {
+    Empty,
+    BadTile { row: usize, col: usize, ch: char },
+    RaggedRow(usize),
+    NoStart,
+    MultipleStarts,
+    // S links up with fewer than two of its neighbours
+    DeadEndStart,
+    // S links up with enough neighbours, but no way of joining them closes a loop
+    BrokenLoop,
+    // more than one shape for S closes a loop
+    AmbiguousStart(Vec<Tile>),
+}
+impl Display for MapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapError::Empty => write!(f, "empty map"),
+            MapError::BadTile { row, col, ch } => write!(f, "bad tile {ch:?} at {row}:{col}"),
+            MapError::RaggedRow(row) => write!(f, "row {row} is a different width"),
+            MapError::NoStart => write!(f, "no S"),
+            MapError::MultipleStarts => write!(f, "more than one S"),
+            MapError::DeadEndStart => write!(f, "S is a dead end"),
+            MapError::BrokenLoop => write!(f, "S isn't on a closed loop"),
+            MapError::AmbiguousStart(shapes) => write!(f, "S could be any of {shapes:?}"),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct TileMap {
     rows: Vec<Tile>,
@@ -72,83 +137,253 @@ struct TileMap {
     start: usize,
 }
 impl TileMap {
-    fn print(&self) {
-        let mut main_loop = self.get_loop();
-        main_loop.sort();
-
-        for r in 0..self.height {
-            for c in 0..self.width {
-                let pos = r * self.width + c;
-
-                if main_loop.binary_search(&pos).is_ok() {
-                    print!(
-                        "{}",
-                        match self.rows[pos] {
-                            Tile::Vertical => '│',
-                            Tile::Horizontal => '─',
-                            Tile::BottomLeft => '└',
-                            Tile::BottomRight => '┘',
-                            Tile::TopLeft => '┌',
-                            Tile::TopRight => '┐',
-                            Tile::Nothing => panic!(),
-                            Tile::UnkStarting => 'S',
-                        }
-                    );
+    fn print(&self, main_loop: &[usize]) {
+        let classes = self.classify(main_loop);
+        for (tiles, classes) in self.rows.chunks(self.width).zip(classes.chunks(self.width)) {
+            for (tile, class) in tiles.iter().zip(classes) {
+                let ch = match class {
+                    EncloseResult::OnMainLoop => match tile {
+                        Tile::Vertical => '│',
+                        Tile::Horizontal => '─',
+                        Tile::BottomLeft => '└',
+                        Tile::BottomRight => '┘',
+                        Tile::TopLeft => '┌',
+                        Tile::TopRight => '┐',
+                        Tile::Nothing => panic!(),
+                        Tile::UnkStarting => 'S',
+                    },
+                    EncloseResult::Enclosed => '*',
+                    EncloseResult::NotEnclosed => ' ',
+                };
+                print!("{ch}");
+            }
+            println!();
+        }
+    }
+
+    // same crossing rule as is_enclosed, but one sweep per row covers every tile
+    fn classify(&self, main_loop: &[usize]) -> Vec<EncloseResult> {
+        let mut on_loop = vec![false; self.rows.len()];
+        for pos in main_loop {
+            on_loop[*pos] = true;
+        }
+
+        let mut result = Vec::with_capacity(self.rows.len());
+        for (tiles, on_loop) in self.rows.chunks(self.width).zip(on_loop.chunks(self.width)) {
+            let mut is_enclosed = false;
+            for (tile, on_loop) in tiles.iter().zip(on_loop) {
+                if *on_loop {
+                    result.push(EncloseResult::OnMainLoop);
+                    if *tile == Tile::Vertical
+                        || *tile == Tile::BottomLeft
+                        || *tile == Tile::BottomRight
+                    {
+                        is_enclosed = !is_enclosed;
+                    }
+                } else if is_enclosed {
+                    result.push(EncloseResult::Enclosed);
                 } else {
-                    print!(
-                        "{}",
-                        if self.is_enclosed(pos, &main_loop) == EncloseResult::Enclosed {
-                            '*'
-                        } else {
-                            ' '
-                        }
-                    );
+                    result.push(EncloseResult::NotEnclosed);
                 }
             }
-            println!();
         }
+        result
     }
 
-    fn resolve_start(&mut self) {
+    // main_loop in walking order from S, so its middle is the farthest point
+    fn to_svg(&self, main_loop: &[usize], marks: SvgMarks) -> String {
+        const CELL: usize = 10;
+        let center = |pos: usize| {
+            (
+                (pos % self.width) * CELL + CELL / 2,
+                (pos / self.width) * CELL + CELL / 2,
+            )
+        };
+
+        let (width, height) = (self.width * CELL, self.height * CELL);
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">"
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "  <rect width=\"{width}\" height=\"{height}\" fill=\"lightsteelblue\"/>"
+        )
+        .unwrap();
+
+        for (pos, class) in self.classify(main_loop).into_iter().enumerate() {
+            if class == EncloseResult::Enclosed {
+                writeln!(
+                    svg,
+                    "  <rect x=\"{}\" y=\"{}\" width=\"{CELL}\" height=\"{CELL}\" fill=\"gold\"/>",
+                    (pos % self.width) * CELL,
+                    (pos / self.width) * CELL
+                )
+                .unwrap();
+            }
+        }
+
+        let points = main_loop
+            .iter()
+            .map(|pos| {
+                let (x, y) = center(*pos);
+                format!("{x},{y}")
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            svg,
+            "  <polygon points=\"{points}\" fill=\"none\" stroke=\"firebrick\" stroke-width=\"{}\" stroke-linejoin=\"round\"/>",
+            CELL / 3
+        )
+        .unwrap();
+
+        let mut mark = |pos: usize, colour: &str| {
+            let (x, y) = center(pos);
+            writeln!(
+                svg,
+                "  <circle cx=\"{x}\" cy=\"{y}\" r=\"{}\" fill=\"{colour}\"/>",
+                CELL / 2
+            )
+            .unwrap();
+        };
+        if marks.start {
+            mark(self.start, "forestgreen");
+        }
+        if marks.farthest && !main_loop.is_empty() {
+            mark(main_loop[main_loop.len() / 2], "purple");
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn resolve_start(&mut self) -> Result<(), MapError> {
         use Direction::*;
-        let real_start =
-            match &[Up, Down, Left, Right].map(|d| self.try_follow_link(self.start, d).is_some()) {
-                &[true, true, false, false] => Tile::Vertical,
-                &[true, false, true, false] => Tile::BottomRight,
-                &[true, false, false, true] => Tile::BottomLeft,
-                &[false, true, true, false] => Tile::TopRight,
-                &[false, true, false, true] => Tile::TopLeft,
-                &[false, false, true, true] => Tile::Horizontal,
-                _ => panic!(),
-            };
 
-        self.rows[self.start] = real_start;
+        let connections = [Up, Down, Left, Right]
+            .into_iter()
+            .filter(|d| self.try_follow_link(self.start, *d).is_some())
+            .count();
+        if connections < 2 {
+            return Err(MapError::DeadEndStart);
+        }
+
+        // with three or four connections, some of them can be junk that doesn't lead back round
+        let mut closing = Vec::new();
+        for shape in Tile::SHAPES {
+            self.rows[self.start] = shape;
+            if self.trace_loop(self.start).is_ok() {
+                closing.push(shape);
+            }
+        }
+
+        match closing[..] {
+            [shape] => {
+                self.rows[self.start] = shape;
+                Ok(())
+            }
+            [] => {
+                self.rows[self.start] = Tile::UnkStarting;
+                Err(MapError::BrokenLoop)
+            }
+            _ => {
+                self.rows[self.start] = Tile::UnkStarting;
+                Err(MapError::AmbiguousStart(closing))
+            }
+        }
+    }
+
+    // follows the pipes out of start until they come back round to it, or gives back the
+    // tiles it got through before running into a dead end
+    fn trace_loop(&self, start: usize) -> Result<Vec<usize>, Vec<usize>> {
+        self.walk(start, |_| false)
     }
 
-    fn get_loop(&self) -> Vec<usize> {
+    // like trace_loop, but also gives up on reaching a tile that stop says is already dealt with
+    fn walk(&self, start: usize, stop: impl Fn(usize) -> bool) -> Result<Vec<usize>, Vec<usize>> {
         use Direction::*;
 
         let mut result = Vec::new();
-        let mut cur_pos = self.start;
-        let mut last_dir = Direction::Up;
-        'find_next: while result.is_empty() || cur_pos != self.start {
-            for dir in &[Up, Down, Left, Right] {
-                if *dir != last_dir.opposite() {
-                    if let Some(new_pos) = self.try_follow_link(cur_pos, *dir) {
-                        result.push(cur_pos);
-                        last_dir = *dir;
-                        cur_pos = new_pos;
-                        continue 'find_next;
-                    }
-                }
+        let mut cur_pos = start;
+        let mut last_dir = None;
+        loop {
+            result.push(cur_pos);
+
+            let Some(dir) = [Up, Down, Left, Right].into_iter().find(|dir| {
+                self.rows[cur_pos].connects_in_direction(*dir) && last_dir != Some(dir.opposite())
+            }) else {
+                return Err(result);
+            };
+            let Some(new_pos) = self.try_follow_link(cur_pos, dir) else {
+                return Err(result);
+            };
+
+            if new_pos == start {
+                return Ok(result);
+            } else if stop(new_pos) || result.len() >= self.rows.len() {
+                // only possible through an unresolved S
+                return Err(result);
             }
+            last_dir = Some(dir);
+            cur_pos = new_pos;
+        }
+    }
+
+    fn get_loop(&self) -> Result<Vec<usize>, MapError> {
+        self.trace_loop(self.start)
+            .map_err(|_| MapError::BrokenLoop)
+    }
+
+    // every closed loop in the map, not just the one through S
+    fn loops(&self) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.rows.len()];
+        let mut result = Vec::new();
 
-            panic!("loop broken: {cur_pos} {last_dir:?}");
+        for pos in 0..self.rows.len() {
+            if visited[pos] || matches!(self.rows[pos], Tile::Nothing | Tile::UnkStarting) {
+                continue;
+            }
+
+            // a walk that dead-ends can't have touched a closed loop, since a tile on one only
+            // links up with its two neighbours along it, so none of it needs walking again.
+            // Closed loops are marked in full, so running into a visited tile means the rest of
+            // the way has already been ruled out too.
+            let (walked, closed) = match self.walk(pos, |next| visited[next]) {
+                Ok(walked) => (walked, true),
+                Err(walked) => (walked, false),
+            };
+            for pos in &walked {
+                visited[*pos] = true;
+            }
+            if closed {
+                result.push(walked);
+            }
         }
 
         result
     }
 
+    // shoelace formula for the area inside the loop, then Pick's theorem (A = I + B/2 - 1) to
+    // turn it into the number of tiles strictly inside
+    fn enclosed_area(&self, main_loop: &[usize]) -> usize {
+        let coords = |pos: usize| ((pos / self.width) as i64, (pos % self.width) as i64);
+
+        let twice_area = main_loop
+            .iter()
+            .zip(main_loop.iter().cycle().skip(1))
+            .map(|(a, b)| {
+                let ((r1, c1), (r2, c2)) = (coords(*a), coords(*b));
+                c1 * r2 - c2 * r1
+            })
+            .sum::<i64>()
+            .unsigned_abs() as usize;
+
+        (twice_area + 2 - main_loop.len()) / 2
+    }
+
     fn count_enclosed_tiles(&self, ordered_main_loop: &[usize]) -> usize {
         let mut result = 0;
 
@@ -212,18 +447,22 @@ impl TileMap {
     }
 }
 
-fn main() {
-    let mut start = None;
-    let mut width = None;
-    let rows = std::fs::read_to_string("input")
-        .unwrap()
-        .lines()
-        .enumerate()
-        .map(|(row, line)| {
-            let result: Vec<_> = line
-                .chars()
-                .enumerate()
-                .map(|(col, ch)| match ch {
+impl FromStr for TileMap {
+    type Err = MapError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rows = Vec::new();
+        let mut width = None;
+        let mut start = None;
+
+        for (row, line) in s.lines().enumerate() {
+            let len = line.chars().count();
+            if *width.get_or_insert(len) != len {
+                return Err(MapError::RaggedRow(row + 1));
+            }
+
+            for (col, ch) in line.chars().enumerate() {
+                rows.push(match ch {
                     '|' => Tile::Vertical,
                     '-' => Tile::Horizontal,
                     'L' => Tile::BottomLeft,
@@ -232,34 +471,373 @@ fn main() {
                     'F' => Tile::TopLeft,
                     '.' => Tile::Nothing,
                     'S' => {
-                        start = Some(row * width.unwrap_or(0) + col);
+                        if start.replace(rows.len()).is_some() {
+                            return Err(MapError::MultipleStarts);
+                        }
                         Tile::UnkStarting
                     }
-                    x => panic!("{row} {col} {x}"),
-                })
-                .collect();
-
-            width = Some(result.len());
+                    ch => {
+                        return Err(MapError::BadTile {
+                            row: row + 1,
+                            col: col + 1,
+                            ch,
+                        })
+                    }
+                });
+            }
+        }
 
-            result
+        let width = width.filter(|width| *width > 0).ok_or(MapError::Empty)?;
+        Ok(TileMap {
+            height: rows.len() / width,
+            rows,
+            width,
+            start: start.ok_or(MapError::NoStart)?,
         })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MazeConfig {
+    width: usize,
+    height: usize,
+    // nodes in the random tree the loop is drawn around; the loop is 4 * nodes * thickness long
+    nodes: usize,
+    // tiles across each part of the tree, which needs to be at least 2 to enclose anything
+    thickness: usize,
+    // chance in 100 of a distractor pipe on each tile off the loop
+    junk_percent: usize,
+    seed: u64,
+}
+
+#[derive(Debug)]
+struct Maze {
+    map: String,
+    loop_length: usize,
+    enclosed: usize,
+}
+
+// Grows a random tree on a coarse grid, thickens it into a solid shape and lays the loop along
+// the shape's outline. The shape has no holes and no two parts touch at just a corner, so the
+// outline is one closed loop that never crosses itself.
+fn generate(config: &MazeConfig) -> Result<Maze, String> {
+    use Direction::*;
+
+    let MazeConfig {
+        width,
+        height,
+        nodes,
+        thickness,
+        junk_percent,
+        seed,
+    } = *config;
+    let mut rng = Rng::new(seed);
+
+    // tiles sit on the corners of the cells the shape is made of
+    let (cells_x, cells_y) = (width.saturating_sub(1), height.saturating_sub(1));
+    // blocks of cells across, which alternate between nodes and the edges between them
+    let blocks = |cells: usize| cells.checked_div(thickness).unwrap_or(0);
+    let (nodes_x, nodes_y) = (blocks(cells_x).div_ceil(2), blocks(cells_y).div_ceil(2));
+    if nodes == 0 || nodes > nodes_x * nodes_y {
+        return Err(format!(
+            "{nodes} nodes of thickness {thickness} don't fit in {width}x{height}"
+        ));
+    }
+
+    let mut in_tree = vec![false; nodes_x * nodes_y];
+    let mut edges = Vec::new();
+    let mut candidates = Vec::new();
+    let neighbours = |node: usize| {
+        let (x, y) = (node % nodes_x, node / nodes_x);
+        [
+            (x > 0).then(|| node - 1),
+            (x + 1 < nodes_x).then(|| node + 1),
+            (y > 0).then(|| node - nodes_x),
+            (y + 1 < nodes_y).then(|| node + nodes_x),
+        ]
+        .into_iter()
         .flatten()
-        .collect::<Vec<Tile>>();
-
-    let rows_len = rows.len();
-    let mut map = TileMap {
-        rows,
-        width: width.unwrap(),
-        height: rows_len / width.unwrap(),
-        start: start.unwrap(),
     };
 
-    map.resolve_start();
-    map.print();
+    let root = rng.below(in_tree.len());
+    in_tree[root] = true;
+    candidates.extend(neighbours(root).map(|to| (root, to)));
+    for _ in 1..nodes {
+        loop {
+            let (from, to) = candidates.swap_remove(rng.below(candidates.len()));
+            if !in_tree[to] {
+                in_tree[to] = true;
+                edges.push((from, to));
+                candidates.extend(neighbours(to).map(|next| (to, next)));
+                break;
+            }
+        }
+    }
+
+    let mut cells = vec![false; cells_x * cells_y];
+    let mut fill = |(x, y): (usize, usize)| {
+        for cy in y * thickness..(y + 1) * thickness {
+            for cx in x * thickness..(x + 1) * thickness {
+                cells[cy * cells_x + cx] = true;
+            }
+        }
+    };
+    // nodes take the even blocks, and an edge the block between its two nodes
+    for node in (0..in_tree.len()).filter(|node| in_tree[*node]) {
+        fill((node % nodes_x * 2, node / nodes_x * 2));
+    }
+    for (a, b) in edges {
+        fill((a % nodes_x + b % nodes_x, a / nodes_x + b / nodes_x));
+    }
+
+    let cell = |x: usize, y: usize| x < cells_x && y < cells_y && cells[y * cells_x + x];
+    let mut tiles = vec![Tile::Nothing; width * height];
+    let mut on_loop = Vec::new();
+    let mut enclosed = 0;
+    for y in 0..height {
+        for x in 0..width {
+            // the four cells around this corner
+            let up_left = x > 0 && y > 0 && cell(x - 1, y - 1);
+            let up_right = y > 0 && cell(x, y - 1);
+            let down_left = x > 0 && cell(x - 1, y);
+            let down_right = cell(x, y);
+
+            // the outline runs between cells on different sides of it
+            let outline = [
+                up_left != up_right,
+                down_left != down_right,
+                up_left != down_left,
+                up_right != down_right,
+            ];
+            let tile = match outline {
+                [true, true, false, false] => Tile::Vertical,
+                [true, false, true, false] => Tile::BottomRight,
+                [true, false, false, true] => Tile::BottomLeft,
+                [false, true, true, false] => Tile::TopRight,
+                [false, true, false, true] => Tile::TopLeft,
+                [false, false, true, true] => Tile::Horizontal,
+                [false, false, false, false] => {
+                    if up_left {
+                        enclosed += 1;
+                    }
+                    if rng.below(100) < junk_percent {
+                        Tile::SHAPES[rng.below(Tile::SHAPES.len())]
+                    } else {
+                        Tile::Nothing
+                    }
+                }
+                _ => unreachable!("outline touches itself at {x},{y}"),
+            };
+            tiles[y * width + x] = tile;
+            if outline.contains(&true) {
+                on_loop.push(y * width + x);
+            }
+        }
+    }
+
+    let map = TileMap {
+        rows: tiles,
+        width,
+        height,
+        start: on_loop[rng.below(on_loop.len())],
+    };
+    let mut tiles = map.rows.clone();
+    tiles[map.start] = Tile::UnkStarting;
+    // junk pointing into S could give it a second way round, so clear it away
+    for dir in [Up, Down, Left, Right] {
+        if let Some(pos) = map.move_in_dir(map.start, dir) {
+            if !on_loop.contains(&pos) && tiles[pos].connects_in_direction(dir.opposite()) {
+                tiles[pos] = Tile::Nothing;
+            }
+        }
+    }
+
+    let map = tiles
+        .chunks(width)
+        .map(|row| row.iter().map(|tile| tile.to_char()).collect::<String>() + "\n")
+        .collect();
+    Ok(Maze {
+        map,
+        loop_length: on_loop.len(),
+        enclosed,
+    })
+}
+
+fn maze_config(args: &[&str]) -> MazeConfig {
+    let [seed, width, height, nodes, rest @ ..] = args else {
+        panic!("expected <seed> <width> <height> <nodes> [thickness]");
+    };
+    MazeConfig {
+        width: width.parse().unwrap(),
+        height: height.parse().unwrap(),
+        nodes: nodes.parse().unwrap(),
+        thickness: rest.first().map_or(2, |t| t.parse().unwrap()),
+        junk_percent: 50,
+        seed: seed.parse().unwrap(),
+    }
+}
+
+fn main() {
+    let mut marks = SvgMarks {
+        start: true,
+        farthest: true,
+    };
+    let mut command = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--no-start" => marks.start = false,
+            "--no-farthest" => marks.farthest = false,
+            _ => command.push(arg),
+        }
+    }
+    let command = command.iter().map(String::as_str).collect::<Vec<_>>();
+
+    // "generate <seed> ..." prints a random maze, and "stress <mazes> ..." solves that many of
+    // them against their expected answers
+    match command[..] {
+        ["generate", ref args @ ..] => {
+            let maze = generate(&maze_config(args)).unwrap_or_else(|e| panic!("{e}"));
+            print!("{}", maze.map);
+            eprintln!(
+                "loop length {}, farthest {}, enclosed {}",
+                maze.loop_length,
+                maze.loop_length / 2,
+                maze.enclosed
+            );
+            return;
+        }
+        ["stress", mazes, ref args @ ..] => {
+            let mut config = maze_config(&[&["0"], args].concat());
+            let begin = std::time::Instant::now();
+            for seed in 0..mazes.parse().unwrap() {
+                config.seed = seed;
+                let maze = generate(&config).unwrap_or_else(|e| panic!("{e}"));
+
+                let mut map: TileMap = maze.map.parse().unwrap_or_else(|e| panic!("{e}"));
+                map.resolve_start()
+                    .unwrap_or_else(|e| panic!("seed {seed}: {e}"));
+                let main_loop = map.get_loop().unwrap_or_else(|e| panic!("{e}"));
+                assert_eq!(
+                    main_loop.len(),
+                    maze.loop_length,
+                    "seed {seed}: loop length"
+                );
+                assert_eq!(
+                    map.enclosed_area(&main_loop),
+                    maze.enclosed,
+                    "seed {seed}: enclosed tiles"
+                );
+            }
+            println!("{mazes} mazes solved in {:?}", begin.elapsed());
+            return;
+        }
+        _ => {}
+    }
+
+    let mut map: TileMap = std::fs::read_to_string("input")
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|e| panic!("{e}"));
+    map.resolve_start().unwrap_or_else(|e| panic!("{e}"));
+
+    let main_loop = map.get_loop().unwrap_or_else(|e| panic!("{e}"));
+    map.print(&main_loop);
+
+    let loops = map.loops();
+    println!(
+        "{} closed loops, lengths {:?}",
+        loops.len(),
+        loops.iter().map(|l| l.len()).collect::<Vec<_>>()
+    );
 
-    let mut main_loop = map.get_loop();
     println!("{}", main_loop.len() / 2);
 
-    main_loop.sort();
-    println!("{}", map.count_enclosed_tiles(&main_loop));
+    println!("{}", map.enclosed_area(&main_loop));
+
+    match command[..] {
+        [] => {}
+        // also counts by ray-casting from every tile, which is much slower
+        ["check"] => {
+            let area = map.enclosed_area(&main_loop);
+            let mut ordered_main_loop = main_loop.clone();
+            ordered_main_loop.sort();
+            let ray_cast = map.count_enclosed_tiles(&ordered_main_loop);
+            assert_eq!(area, ray_cast, "shoelace and ray-cast counts disagree");
+            println!("ray-cast agrees");
+        }
+        ["svg", path] => std::fs::write(path, map.to_svg(&main_loop, marks)).unwrap(),
+        _ => panic!(
+            "usage: [--no-start] [--no-farthest] [check | svg <file> | generate <seed> <width> \
+             <height> <nodes> [thickness] | stress <mazes> <width> <height> <nodes> [thickness]]"
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: &[(&str, usize, usize)] = &[
+        (".....\n.S-7.\n.|.|.\n.L-J.\n.....\n", 4, 1),
+        ("..F7.\n.FJ|.\nSJ.L7\n|F--J\nLJ...\n", 8, 1),
+        (
+            "...........\n.S-------7.\n.|F-----7|.\n.||.....||.\n.||.....||.\n\
+             .|L-7.F-J|.\n.|..|.|..|.\n.L--J.L--J.\n...........\n",
+            23,
+            4,
+        ),
+        (
+            "..........\n.S------7.\n.|F----7|.\n.||....||.\n.||....||.\n.|L-7F-J|.\n\
+             .|..||..|.\n.L--JL--J.\n..........\n",
+            22,
+            4,
+        ),
+        (
+            ".F----7F7F7F7F-7....\n.|F--7||||||||FJ....\n.||.FJ||||||||L7....\n\
+             FJL7L7LJLJ||LJ.L-7..\nL--J.L7...LJS7F-7L7.\n....F-J..F7FJ|L7L7L7\n\
+             ....L7.F7||L7|.L7L7|\n.....|FJLJ|FJ|F7|.LJ\n....FJL-7.||.||||...\n\
+             ....L---J.LJ.LJLJ...\n",
+            70,
+            8,
+        ),
+        (
+            "FF7FSF7F7F7F7F7F---7\nL|LJ||||||||||||F--J\nFL-7LJLJ||||||LJL-77\n\
+             F--JF--7||LJLJ7F7FJ-\nL---JF-JLJ.||-FJLJJ7\n|F|F-JF---7F7-L7L|7|\n\
+             |FFJF7L7F-JF7|JL---7\n7-L-JL7||F7|L7F-7F7|\nL.L7LFJ|||||FJL7||LJ\n\
+             L7JLJL-JLJLJL--JLJ.L\n",
+            80,
+            10,
+        ),
+    ];
+
+    #[test]
+    fn shoelace_matches_ray_cast() {
+        for (input, farthest, enclosed) in SAMPLES {
+            let mut map: TileMap = input.parse().unwrap();
+            map.resolve_start().unwrap();
+            let main_loop = map.get_loop().unwrap();
+            assert_eq!(main_loop.len() / 2, *farthest, "{input}");
+
+            let mut ordered_main_loop = main_loop.clone();
+            ordered_main_loop.sort();
+            assert_eq!(map.enclosed_area(&main_loop), *enclosed, "{input}");
+            assert_eq!(
+                map.count_enclosed_tiles(&ordered_main_loop),
+                *enclosed,
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn loops_skip_dead_ends() {
+        let map: TileMap = "F7.F-7-\nLJ-|.|7\n.|.L-JS\n".parse().unwrap();
+        let mut lengths = map.loops().iter().map(|l| l.len()).collect::<Vec<_>>();
+        lengths.sort();
+        assert_eq!(lengths, [4, 8]);
+
+        // one long dead end, which only gets walked once
+        let map: TileMap = format!("S{}", "-".repeat(200_000)).parse().unwrap();
+        assert!(map.loops().is_empty());
+    }
 }
@@ -1,7 +1,28 @@
+use std::collections::HashSet;
+
 fn manhattan_distance((r1, c1): (usize, usize), (r2, c2): (usize, usize)) -> usize {
     r2.abs_diff(r1) + c2.abs_diff(c1)
 }
 
+// result[i] = how many of the first i rows (or columns) have no galaxies
+fn empty_before(len: usize, occupied: impl Iterator<Item = usize>) -> Vec<usize> {
+    let mut is_occupied = vec![false; len];
+    for i in occupied {
+        is_occupied[i] = true;
+    }
+
+    let mut result = Vec::with_capacity(len + 1);
+    let mut count = 0;
+    result.push(count);
+    for is_occupied in is_occupied {
+        if !is_occupied {
+            count += 1;
+        }
+        result.push(count);
+    }
+    result
+}
+
 #[derive(Debug, Clone)]
 struct Image {
     galaxies: Vec<(usize, usize)>,
@@ -10,6 +31,8 @@ struct Image {
 }
 impl Image {
     fn print(&self) {
+        let galaxies = self.galaxies.iter().collect::<HashSet<_>>();
+
         for i in 0..self.width {
             print!("{}", i % 10);
         }
@@ -17,7 +40,7 @@ impl Image {
 
         for row in 0..self.height {
             for col in 0..self.width {
-                if self.galaxies.contains(&(row, col)) {
+                if galaxies.contains(&(row, col)) {
                     print!("#");
                 } else {
                     print!(".");
@@ -29,56 +52,49 @@ impl Image {
         println!();
     }
 
-    fn galaxy_pairs(&self) -> Vec<((usize, usize), (usize, usize))> {
-        let mut result = Vec::new();
+    fn galaxy_pairs(&self) -> impl Iterator<Item = ((usize, usize), (usize, usize))> + '_ {
+        self.galaxies
+            .iter()
+            .enumerate()
+            .flat_map(|(i, g1)| self.galaxies[i + 1..].iter().map(|g2| (*g1, *g2)))
+    }
 
-        for i in 0..self.galaxies.len() {
-            for j in (i + 1)..self.galaxies.len() {
-                result.push((self.galaxies[i], self.galaxies[j]));
+    // sum of the Manhattan distances between every pair, one axis at a time: with the
+    // coordinates sorted, the i-th one is the larger end of i pairs
+    fn total_distance(&self) -> u128 {
+        let axis_total = |mut coords: Vec<usize>| {
+            coords.sort_unstable();
+            let mut below = 0u128;
+            let mut result = 0u128;
+            for (i, x) in coords.into_iter().enumerate() {
+                result += x as u128 * i as u128 - below;
+                below += x as u128;
             }
-        }
+            result
+        };
 
-        result
+        axis_total(self.galaxies.iter().map(|(row, _)| *row).collect())
+            + axis_total(self.galaxies.iter().map(|(_, col)| *col).collect())
     }
 
     fn expand(&self, scale: usize) -> Image {
-        let mut updated = self.clone();
-
-        // expand rows
-        for row in 0..self.height {
-            if !self.galaxies.iter().any(|(test_row, _)| row == *test_row) {
-                updated.height += scale;
-
-                // increase row number of all galaxies after this row
-                for (_, (updated_row, _)) in self
-                    .galaxies
-                    .iter()
-                    .zip(updated.galaxies.iter_mut())
-                    .filter(|((orig_row, _), _)| *orig_row > row)
-                {
-                    *updated_row += scale;
-                }
-            }
-        }
-
-        // expand columns
-        for col in 0..self.width {
-            if !self.galaxies.iter().any(|(_, test_col)| col == *test_col) {
-                updated.width += scale;
-
-                // increase column number of all galaxies after this row
-                for (_, (_, updated_col)) in self
-                    .galaxies
-                    .iter()
-                    .zip(updated.galaxies.iter_mut())
-                    .filter(|((_, orig_col), _)| *orig_col > col)
-                {
-                    *updated_col += scale;
-                }
-            }
+        let empty_rows = empty_before(self.height, self.galaxies.iter().map(|(row, _)| *row));
+        let empty_cols = empty_before(self.width, self.galaxies.iter().map(|(_, col)| *col));
+
+        Image {
+            galaxies: self
+                .galaxies
+                .iter()
+                .map(|(row, col)| {
+                    (
+                        row + empty_rows[*row] * scale,
+                        col + empty_cols[*col] * scale,
+                    )
+                })
+                .collect(),
+            width: self.width + empty_cols[self.width] * scale,
+            height: self.height + empty_rows[self.height] * scale,
         }
-
-        updated
     }
 }
 
@@ -87,16 +103,15 @@ fn main() {
     let galaxies = {
         text.lines()
             .enumerate()
-            .map(|(row, line)| {
+            .flat_map(|(row, line)| {
                 line.chars().enumerate().filter_map(move |(col, char)| {
                     if char == '#' {
-                        Some((row.clone(), col))
+                        Some((row, col))
                     } else {
                         None
                     }
                 })
             })
-            .flatten()
             .collect::<Vec<_>>()
     };
 
@@ -107,19 +122,20 @@ fn main() {
     };
 
     image.print();
-    let result1: usize = image
-        .expand(1)
-        .galaxy_pairs()
-        .into_iter()
-        .map(|(g1, g2)| manhattan_distance(g1, g2))
-        .sum();
-    println!("{result1}");
-
-    let result2: usize = image
-        .expand(999_999)
-        .galaxy_pairs()
-        .into_iter()
-        .map(|(g1, g2)| manhattan_distance(g1, g2))
-        .sum();
-    println!("{result2}");
+
+    let (expanded1, expanded2) = (image.expand(1), image.expand(999_999));
+    println!("{}", expanded1.total_distance());
+    println!("{}", expanded2.total_distance());
+
+    // "check" also sums every pair one at a time, which is quadratic in the number of galaxies
+    if std::env::args().nth(1).as_deref() == Some("check") {
+        for expanded in [expanded1, expanded2] {
+            let pairwise: usize = expanded
+                .galaxy_pairs()
+                .map(|(g1, g2)| manhattan_distance(g1, g2))
+                .sum();
+            assert_eq!(pairwise as u128, expanded.total_distance());
+        }
+        println!("pairwise sums agree");
+    }
 }
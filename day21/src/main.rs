@@ -1,6 +1,6 @@
 use std::{collections::HashSet, ops::Index, rc::Rc, str::FromStr};
 
-use nalgebra::{Matrix3, Vector3};
+use poly::Polynomial;
 
 #[derive(Debug, Clone, Copy)]
 enum Tile {
@@ -119,42 +119,22 @@ fn main() {
     let offset = p2_cycles % board_height;
     let p2_queue = bounded_bfs(&map, dbg!(offset), p2_queue);
 
-    let p2_queue = bounded_bfs(&map, board_height, p2_queue);
-    let sample_1 = p2_queue.len();
-    let p2_queue = bounded_bfs(&map, board_height, p2_queue);
-    let sample_2 = p2_queue.len();
-    let p2_queue = bounded_bfs(&map, board_height, p2_queue);
-    let sample_3 = p2_queue.len();
-
-    dbg!(sample_1, sample_2, sample_3);
-
-    // Construct a quadratic from f(x), where x = the offset + number_of_board_heights
-
-    // sample_1 = 1a + 1b + c // (x = 1)
-    // sample_2 = 4a + 2b + c // (x = 2)
-    // sample_3 = 9a + 3b + c // (x = 3)
-    // in matrix form:
-    // [1, 1, 1]   [a]   [sample_1]
-    // [4, 2, 1] * [b] = [sample_2]
-    // [9, 3, 1]   [c]   [sample_3]
-    #[cfg_attr(rustfmt, rustfmt_skip)]
-    let coeffs = Matrix3::<f64>::new(
-        1.0, 1.0, 1.0,
-        4.0, 2.0, 1.0,
-        9.0, 3.0, 1.0,
-    );
-    let decomp = coeffs.lu();
-    let result = Vector3::new(sample_1 as f64, sample_2 as f64, sample_3 as f64);
-    let solution = decomp.solve(&result).unwrap();
-    let &[a, b, c] = solution.as_slice() else {
-        panic!()
-    };
-
-    let a = a.round() as usize;
-    let b = b.round() as usize;
-    let c = c.round() as usize;
+    // f(x) = reachable tiles after the offset + x board heights, sampled at x = 1, 2, 3 to fit
+    // the quadratic and at x = 4 to check it really is one
+    let mut samples = Vec::new();
+    let mut p2_queue = p2_queue;
+    for x in 1..=4 {
+        p2_queue = bounded_bfs(&map, board_height, p2_queue);
+        samples.push((x, p2_queue.len() as i128));
+    }
+    dbg!(&samples);
+
+    let quadratic = Polynomial::fit_degree(&samples, 2).unwrap_or_else(|e| panic!("{e}"));
+    println!("f(x) = {quadratic}");
 
     let x = (p2_cycles - offset) / board_height;
-    dbg!(a, b, c, x, x * board_height + offset, p2_cycles);
-    dbg!(a * x * x + b * x + c);
+    dbg!(x, x * board_height + offset, p2_cycles);
+    dbg!(quadratic
+        .eval_integer(x as i128)
+        .unwrap_or_else(|e| panic!("{e}")));
 }
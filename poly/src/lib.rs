@@ -0,0 +1,455 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FitError {
+    NoSamples,
+    DuplicateX(i128),
+    // the fitted polynomial doesn't go through one of the extra samples
+    Mismatch {
+        x: i128,
+        expected: i128,
+        actual: Rational,
+    },
+    NotInteger {
+        x: i128,
+        value: Rational,
+    },
+    Overflow,
+}
+impl Display for FitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FitError::NoSamples => write!(f, "no samples to fit"),
+            FitError::DuplicateX(x) => write!(f, "x = {x} sampled twice"),
+            FitError::Mismatch {
+                x,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "sample at x = {x} is {expected}, but the fit gives {actual}"
+            ),
+            FitError::NotInteger { x, value } => write!(f, "value at x = {x} is {value}"),
+            FitError::Overflow => write!(f, "arithmetic overflowed"),
+        }
+    }
+}
+
+// always in lowest terms with a positive denominator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rational {
+    num: i128,
+    den: i128,
+}
+impl Rational {
+    pub const ZERO: Rational = Rational { num: 0, den: 1 };
+
+    pub fn new(num: i128, den: i128) -> Option<Self> {
+        // i128::MIN has no positive counterpart to normalize the sign to
+        if den == 0 || num == i128::MIN || den == i128::MIN {
+            return None;
+        }
+        let divisor = gcd(num, den);
+        let (num, den) = (num / divisor, den / divisor);
+        if den < 0 {
+            Some(Self {
+                num: -num,
+                den: -den,
+            })
+        } else {
+            Some(Self { num, den })
+        }
+    }
+
+    pub const ONE: Rational = Rational { num: 1, den: 1 };
+
+    // None for i128::MIN, like new
+    pub fn integer(value: i128) -> Option<Self> {
+        Self::new(value, 1)
+    }
+
+    pub fn numerator(&self) -> i128 {
+        self.num
+    }
+
+    pub fn denominator(&self) -> i128 {
+        self.den
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.num < 0
+    }
+
+    pub fn abs(&self) -> Self {
+        Self {
+            num: self.num.abs(),
+            den: self.den,
+        }
+    }
+
+    pub fn to_integer(&self) -> Option<i128> {
+        (self.den == 1).then_some(self.num)
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        // scale by the lcm rather than the product to keep intermediates small
+        let divisor = gcd(self.den, other.den);
+        let den = (self.den / divisor).checked_mul(other.den)?;
+        let num = self
+            .num
+            .checked_mul(other.den / divisor)?
+            .checked_add(other.num.checked_mul(self.den / divisor)?)?;
+        Self::new(num, den)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.checked_add(Self {
+            num: other.num.checked_neg()?,
+            den: other.den,
+        })
+    }
+
+    pub fn checked_mul(self, other: Self) -> Option<Self> {
+        // cancel across before multiplying
+        let a = gcd(self.num, other.den);
+        let b = gcd(other.num, self.den);
+        Self::new(
+            (self.num / a).checked_mul(other.num / b)?,
+            (self.den / b).checked_mul(other.den / a)?,
+        )
+    }
+
+    pub fn checked_div(self, other: Self) -> Option<Self> {
+        if other.num == 0 {
+            return None;
+        }
+        self.checked_mul(Self::new(other.den, other.num)?)
+    }
+}
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    // only zero when both are, and 1 keeps the division in Rational::new harmless
+    a.max(1) as i128
+}
+
+// stored in Newton form: c0 + (x - x0) * (c1 + (x - x1) * (c2 + ...)),
+// which is exact to build from samples and to evaluate
+#[derive(Debug, Clone)]
+pub struct Polynomial {
+    nodes: Vec<i128>,
+    coefficients: Vec<Rational>,
+}
+impl Polynomial {
+    // the lowest-degree polynomial through every sample
+    pub fn fit(samples: &[(i128, i128)]) -> Result<Self, FitError> {
+        if samples.is_empty() {
+            return Err(FitError::NoSamples);
+        }
+        for (i, (x, _)) in samples.iter().enumerate() {
+            if samples[..i].iter().any(|(other, _)| other == x) {
+                return Err(FitError::DuplicateX(*x));
+            }
+        }
+
+        let nodes = samples.iter().map(|(x, _)| *x).collect::<Vec<_>>();
+
+        // divided differences, built up in place: after pass j, table[i] = f[x(i-j), ..., x(i)]
+        let mut table = samples
+            .iter()
+            .map(|(_, y)| Rational::integer(*y).ok_or(FitError::Overflow))
+            .collect::<Result<Vec<_>, _>>()?;
+        for j in 1..table.len() {
+            for i in (j..table.len()).rev() {
+                let width = nodes[i]
+                    .checked_sub(nodes[i - j])
+                    .and_then(Rational::integer)
+                    .ok_or(FitError::Overflow)?;
+                table[i] = table[i]
+                    .checked_sub(table[i - 1])
+                    .and_then(|diff| diff.checked_div(width))
+                    .ok_or(FitError::Overflow)?;
+            }
+        }
+
+        // a zero leading coefficient in Newton form means the degree is lower
+        while table.len() > 1 && table.last() == Some(&Rational::ZERO) {
+            table.pop();
+        }
+
+        Ok(Self {
+            nodes,
+            coefficients: table,
+        })
+    }
+
+    // fits the first degree + 1 samples, then checks the fit against the rest
+    pub fn fit_degree(samples: &[(i128, i128)], degree: usize) -> Result<Self, FitError> {
+        let (fitted, extra) = samples.split_at(samples.len().min(degree + 1));
+        let polynomial = Self::fit(fitted)?;
+        polynomial.verify(extra)?;
+        Ok(polynomial)
+    }
+
+    pub fn verify(&self, samples: &[(i128, i128)]) -> Result<(), FitError> {
+        for (x, y) in samples {
+            let actual = self.eval(*x)?;
+            if Rational::integer(*y) != Some(actual) {
+                return Err(FitError::Mismatch {
+                    x: *x,
+                    expected: *y,
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // the zero polynomial counts as degree 0
+    pub fn degree(&self) -> usize {
+        self.coefficients.len() - 1
+    }
+
+    pub fn eval(&self, x: i128) -> Result<Rational, FitError> {
+        let mut result = Rational::ZERO;
+        for (node, coefficient) in self.nodes.iter().zip(&self.coefficients).rev() {
+            let factor = x
+                .checked_sub(*node)
+                .and_then(Rational::integer)
+                .ok_or(FitError::Overflow)?;
+            result = result
+                .checked_mul(factor)
+                .and_then(|result| result.checked_add(*coefficient))
+                .ok_or(FitError::Overflow)?;
+        }
+        Ok(result)
+    }
+
+    pub fn eval_integer(&self, x: i128) -> Result<i128, FitError> {
+        let value = self.eval(x)?;
+        value.to_integer().ok_or(FitError::NotInteger { x, value })
+    }
+
+    // coefficients of 1, x, x^2, ... in the usual power basis
+    pub fn power_coefficients(&self) -> Result<Vec<Rational>, FitError> {
+        let mut result = vec![*self.coefficients.last().unwrap()];
+        for (node, coefficient) in self.nodes.iter().zip(&self.coefficients).rev().skip(1) {
+            // result = result * (x - node) + coefficient
+            let node = Rational::integer(*node).ok_or(FitError::Overflow)?;
+            let mut next = vec![Rational::ZERO; result.len() + 1];
+            for (i, c) in result.iter().enumerate() {
+                next[i + 1] = next[i + 1].checked_add(*c).ok_or(FitError::Overflow)?;
+                next[i] = c
+                    .checked_mul(node)
+                    .and_then(|term| next[i].checked_sub(term))
+                    .ok_or(FitError::Overflow)?;
+            }
+            next[0] = next[0]
+                .checked_add(*coefficient)
+                .ok_or(FitError::Overflow)?;
+            result = next;
+        }
+        Ok(result)
+    }
+}
+impl Display for Polynomial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let coefficients = self.power_coefficients().map_err(|_| std::fmt::Error)?;
+        let mut first = true;
+        for (power, coefficient) in coefficients.iter().enumerate().rev() {
+            if *coefficient == Rational::ZERO && !(first && power == 0) {
+                continue;
+            }
+            let coefficient = if first {
+                *coefficient
+            } else if coefficient.is_negative() {
+                write!(f, " - ")?;
+                coefficient.abs()
+            } else {
+                write!(f, " + ")?;
+                *coefficient
+            };
+            first = false;
+            // a bare x rather than 1x, or -x rather than -1x
+            if power > 0 && coefficient.abs() == Rational::ONE {
+                if coefficient.is_negative() {
+                    write!(f, "-")?;
+                }
+            } else {
+                write!(f, "{coefficient}")?;
+            }
+            match power {
+                0 => {}
+                1 => write!(f, "x")?,
+                _ => write!(f, "x^{power}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn r(num: i128, den: i128) -> Rational {
+        Rational::new(num, den).unwrap()
+    }
+
+    #[test]
+    fn rational_normalisation() {
+        assert_eq!(r(6, -4), r(-3, 2));
+        assert_eq!(r(-6, -4).numerator(), 3);
+        assert_eq!(r(-6, -4).denominator(), 2);
+        assert_eq!(r(0, -7), Rational::ZERO);
+        assert_eq!(Rational::new(1, 0), None);
+        assert_eq!(Rational::new(i128::MIN, 1), None);
+        assert_eq!(Rational::new(1, i128::MIN), None);
+        assert_eq!(Rational::integer(i128::MIN), None);
+        assert_eq!(Rational::integer(i128::MAX), Some(r(i128::MAX, 1)));
+        assert_eq!(r(4, 2).to_integer(), Some(2));
+        assert_eq!(r(1, 2).to_integer(), None);
+    }
+
+    #[test]
+    fn rational_arithmetic() {
+        assert_eq!(r(1, 2).checked_add(r(1, 3)), Some(r(5, 6)));
+        assert_eq!(r(1, 6).checked_add(r(1, 3)), Some(r(1, 2)));
+        assert_eq!(r(1, 2).checked_sub(r(3, 4)), Some(r(-1, 4)));
+        assert_eq!(r(2, 3).checked_mul(r(9, 4)), Some(r(3, 2)));
+        assert_eq!(r(2, 3).checked_div(r(-4, 9)), Some(r(-3, 2)));
+        assert_eq!(r(2, 3).checked_div(Rational::ZERO), None);
+
+        // cancelling first keeps these in range
+        let big = i128::MAX / 2;
+        assert_eq!(r(big, 3).checked_mul(r(3, big)), Some(Rational::ONE));
+        assert_eq!(r(1, big).checked_add(r(1, big)), Some(r(2, big)));
+
+        assert_eq!(r(i128::MAX, 1).checked_add(Rational::ONE), None);
+        assert_eq!(r(i128::MAX, 1).checked_mul(r(2, 1)), None);
+        assert_eq!(r(-i128::MAX, 1).checked_sub(r(2, 1)), None);
+    }
+
+    #[test]
+    fn fit_and_verify() {
+        // x^2 + 1
+        let samples = [(0, 1), (1, 2), (2, 5), (3, 10)];
+        let polynomial = Polynomial::fit(&samples).unwrap();
+        assert_eq!(polynomial.degree(), 2);
+        assert_eq!(polynomial.verify(&[(4, 17), (-3, 10)]), Ok(()));
+        assert_eq!(
+            polynomial.verify(&[(4, 18)]),
+            Err(FitError::Mismatch {
+                x: 4,
+                expected: 18,
+                actual: r(17, 1),
+            })
+        );
+
+        // the samples don't need to be evenly spaced or in order
+        let polynomial = Polynomial::fit(&[(10, 101), (-2, 5), (7, 50)]).unwrap();
+        assert_eq!(polynomial.eval(0), Ok(Rational::ONE));
+
+        // a constant, and the zero polynomial, are degree 0
+        assert_eq!(Polynomial::fit(&[(0, 4), (5, 4)]).unwrap().degree(), 0);
+        assert_eq!(Polynomial::fit(&[(0, 0), (1, 0)]).unwrap().degree(), 0);
+
+        assert!(matches!(Polynomial::fit(&[]), Err(FitError::NoSamples)));
+        assert!(matches!(
+            Polynomial::fit(&[(1, 2), (3, 4), (1, 5)]),
+            Err(FitError::DuplicateX(1))
+        ));
+    }
+
+    #[test]
+    fn fit_degree_checks_the_extra_samples() {
+        let samples = [(1, 1), (2, 4), (3, 9), (4, 16)];
+        let quadratic = Polynomial::fit_degree(&samples, 2).unwrap();
+        assert_eq!(quadratic.degree(), 2);
+
+        assert_eq!(
+            Polynomial::fit_degree(&samples, 1).unwrap_err(),
+            FitError::Mismatch {
+                x: 3,
+                expected: 9,
+                actual: r(7, 1),
+            }
+        );
+        assert_eq!(
+            Polynomial::fit_degree(&[(2, 1), (2, 3), (4, 5)], 1).unwrap_err(),
+            FitError::DuplicateX(2)
+        );
+        // an extra sample at a fitted x is just another check
+        assert_eq!(
+            Polynomial::fit_degree(&[(1, 1), (2, 2), (2, 3)], 1).unwrap_err(),
+            FitError::Mismatch {
+                x: 2,
+                expected: 3,
+                actual: r(2, 1),
+            }
+        );
+    }
+
+    #[test]
+    fn eval_large_and_overflowing() {
+        // x^3 / 3 - x^2 + 11x / 3 + 10 takes integer values at integers
+        let cubic = Polynomial::fit(&[(0, 10), (1, 13), (2, 16), (3, 21)]).unwrap();
+        let x = 1_000_000_000_000i128;
+        assert_eq!(
+            cubic.eval_integer(x),
+            Ok((x * x * x - 3 * x * x + 11 * x) / 3 + 10)
+        );
+        assert_eq!(cubic.eval(1), Ok(r(13, 1)));
+
+        let half = Polynomial::fit(&[(0, 0), (2, 1)]).unwrap();
+        assert_eq!(
+            half.eval_integer(1),
+            Err(FitError::NotInteger {
+                x: 1,
+                value: r(1, 2),
+            })
+        );
+
+        assert_eq!(cubic.eval(i128::MAX / 2), Err(FitError::Overflow));
+        assert_eq!(cubic.eval(i128::MIN), Err(FitError::Overflow));
+        assert!(matches!(
+            Polynomial::fit(&[(0, 0), (1, i128::MAX), (2, -i128::MAX)]),
+            Err(FitError::Overflow)
+        ));
+    }
+
+    #[test]
+    fn power_coefficients_and_display() {
+        let cubic = Polynomial::fit(&[(0, 10), (1, 13), (2, 16), (3, 21)]).unwrap();
+        assert_eq!(
+            cubic.power_coefficients(),
+            Ok(vec![r(10, 1), r(11, 3), r(-1, 1), r(1, 3)])
+        );
+        assert_eq!(cubic.to_string(), "1/3x^3 - x^2 + 11/3x + 10");
+
+        let line = Polynomial::fit(&[(5, -4), (6, -5)]).unwrap();
+        assert_eq!(line.power_coefficients(), Ok(vec![r(1, 1), r(-1, 1)]));
+        assert_eq!(line.to_string(), "-x + 1");
+
+        assert_eq!(Polynomial::fit(&[(3, 0)]).unwrap().to_string(), "0");
+        assert_eq!(Polynomial::fit(&[(0, 0), (1, 1)]).unwrap().to_string(), "x");
+        assert_eq!(
+            Polynomial::fit(&[(-1, 2), (0, 0), (1, 2)])
+                .unwrap()
+                .to_string(),
+            "2x^2"
+        );
+    }
+}